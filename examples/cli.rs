@@ -3,7 +3,8 @@ use config::{Config, ConfigError, File};
 use dirs::config_dir;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use smolkv_client::{Error, QueryBuilder, SmolKv, SortOrder};
+use smolkv_client::checksum::verify_backup_file;
+use smolkv_client::{Checksum, Error, QueryBuilder, SmolKv, SortOrder, TransferProgress};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
@@ -46,8 +47,7 @@ impl Settings {
         let parent_dir = config_path.parent().unwrap();
         fs::create_dir_all(parent_dir)?;
 
-        let toml =
-            toml::to_string(self).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let toml = toml::to_string(self).map_err(std::io::Error::other)?;
 
         fs::write(config_path, toml)
     }
@@ -56,12 +56,12 @@ impl Settings {
         match &self.default_endpoint {
             Some(name) => match self.endpoints.get(name) {
                 Some(config) => Ok((name.clone(), config.clone())),
-                None => Err(Error::BadRequest(format!(
+                None => Err(Error::Config(format!(
                     "Default endpoint '{}' not found in config",
                     name
                 ))),
             },
-            None => Err(Error::BadRequest(
+            None => Err(Error::Config(
                 "No default endpoint set. Use 'endpoint use <name>' to set a default endpoint."
                     .into(),
             )),
@@ -77,20 +77,22 @@ fn get_config_path() -> PathBuf {
 }
 
 // Parse path format for key operations: <collection>/<key>
+// Everything after the first slash is the key, so keys may contain slashes themselves.
 fn parse_key_path(path: &str) -> Result<(String, String), Error> {
-    let parts: Vec<&str> = path.split('/').collect();
-
-    match parts.len() {
-        1 => Err(Error::BadRequest(
+    match path.split_once('/') {
+        Some((collection, key)) if !collection.is_empty() && !key.is_empty() => {
+            Ok((collection.to_string(), key.to_string()))
+        }
+        _ => Err(Error::InvalidInput(
             "Invalid path format. Use <collection>/<key>".into(),
         )),
-        2 => Ok((parts[0].to_string(), parts[1].to_string())),
-        _ => {
-            // If there are more parts, consider the first part as collection and the rest as a key with slashes
-            let collection = parts[0].to_string();
-            let key = parts[1..].join("/");
-            Ok((collection, key))
-        }
+    }
+}
+
+fn print_progress(progress: TransferProgress) {
+    match progress.total {
+        Some(total) => eprint!("\r{}/{} bytes", progress.transferred, total),
+        None => eprint!("\r{} bytes", progress.transferred),
     }
 }
 
@@ -116,12 +118,24 @@ enum Commands {
 
         /// JSON value as string
         value: String,
+
+        /// Only write if the key does not exist yet
+        #[arg(long, conflicts_with = "if_match")]
+        if_absent: bool,
+
+        /// Only write if the key is still at this version (see `get --with-version`)
+        #[arg(long)]
+        if_match: Option<String>,
     },
 
     /// Get a value from a collection
     Get {
         /// Path in format collection/key
         path: String,
+
+        /// Also print the value's version, for use with `put --if-match`
+        #[arg(long)]
+        with_version: bool,
     },
 
     /// Delete a value from a collection
@@ -331,6 +345,13 @@ enum BackupSubcommands {
         #[arg(long)]
         output: Option<String>,
     },
+
+    /// Verify a downloaded backup file against its .sha256 manifest
+    Verify {
+        /// Path to the backup file or to its manifest
+        #[arg(long)]
+        file: String,
+    },
 }
 
 #[derive(Args)]
@@ -381,14 +402,14 @@ impl From<CliSortOrder> for SortOrder {
 async fn main() -> Result<(), Error> {
     let cli = Cli::parse();
     let mut settings =
-        Settings::load().map_err(|e| Error::BadRequest(format!("Failed to load config: {}", e)))?;
+        Settings::load().map_err(|e| Error::Config(format!("Failed to load config: {}", e)))?;
 
     // Handle endpoint commands separately as they don't need a KV client
     if let Commands::Endpoint(args) = &cli.command {
         match &args.command {
             EndpointCommands::Use { name } => {
                 if !settings.endpoints.contains_key(name) {
-                    return Err(Error::BadRequest(format!(
+                    return Err(Error::Config(format!(
                         "Endpoint '{}' not found in config",
                         name
                     )));
@@ -396,7 +417,7 @@ async fn main() -> Result<(), Error> {
                 settings.default_endpoint = Some(name.clone());
                 settings
                     .save()
-                    .map_err(|e| Error::BadRequest(format!("Failed to save config: {}", e)))?;
+                    .map_err(|e| Error::Config(format!("Failed to save config: {}", e)))?;
                 println!("Using endpoint '{}'", name);
                 return Ok(());
             }
@@ -410,7 +431,7 @@ async fn main() -> Result<(), Error> {
                 );
                 settings
                     .save()
-                    .map_err(|e| Error::BadRequest(format!("Failed to save config: {}", e)))?;
+                    .map_err(|e| Error::Config(format!("Failed to save config: {}", e)))?;
                 println!("Endpoint '{}' set successfully", name);
                 return Ok(());
             }
@@ -444,7 +465,7 @@ async fn main() -> Result<(), Error> {
 
                     settings
                         .save()
-                        .map_err(|e| Error::BadRequest(format!("Failed to save config: {}", e)))?;
+                        .map_err(|e| Error::Config(format!("Failed to save config: {}", e)))?;
                     println!("Endpoint '{}' removed successfully", name);
                 } else {
                     println!("Endpoint '{}' not found in config", name);
@@ -454,23 +475,27 @@ async fn main() -> Result<(), Error> {
         }
     }
 
-    // Get the default endpoint for all other commands
-    let (_, endpoint_config) = settings.get_endpoint()?;
-
-    // Create the KV client once
-    let kv = match &endpoint_config.secret {
-        Some(secret) => SmolKv::new(endpoint_config.url, Some(secret.clone())),
-        None => SmolKv::new(endpoint_config.url, None::<String>),
+    // Only commands that talk to the server need an endpoint, so local ones like
+    // `backup verify` keep working without one
+    let kv = || -> Result<SmolKv, Error> {
+        let (_, endpoint_config) = settings.get_endpoint()?;
+        SmolKv::builder(endpoint_config.url)
+            .secret(endpoint_config.secret)
+            .build()
     };
 
-    // Process the command with a single KV client
+    // Process the command
     let res = match &cli.command {
         Commands::Endpoint(_) => unreachable!(), // Already handled
 
         Commands::Collection(cmd) => {
             match &cmd.command {
-                CollectionSubcommands::Create { name } => kv.create_collection(name).await?,
-                CollectionSubcommands::Drop { name } => kv.drop_collection(name).await?,
+                CollectionSubcommands::Create { name } => {
+                    serde_json::to_value(kv()?.create_collection(name).await?)?
+                }
+                CollectionSubcommands::Drop { name } => {
+                    serde_json::to_value(kv()?.drop_collection(name).await?)?
+                }
                 CollectionSubcommands::List {
                     name,
                     query,
@@ -494,22 +519,14 @@ async fn main() -> Result<(), Error> {
                         }
                     }
 
-                    Value::Array(kv.query_collection(name, builder).await?)
+                    Value::Array(kv()?.query_collection(name, builder).await?)
                 }
                 CollectionSubcommands::Watch { name } => {
-                    let resp = kv.subscribe(name).await?;
-                    let mut stream = resp.bytes_stream();
-
-                    while let Some(chunk) = stream.next().await {
-                        match chunk {
-                            Ok(bytes) => {
-                                let text = String::from_utf8_lossy(&bytes);
-                                if let Some(data) = text.strip_prefix("data: ") {
-                                    if let Ok(event) = serde_json::from_str::<Value>(data) {
-                                        println!("{}", serde_json::to_string_pretty(&event)?);
-                                    }
-                                }
-                            }
+                    let mut events = kv()?.subscribe_events(name);
+
+                    while let Some(event) = events.next().await {
+                        match event {
+                            Ok(event) => println!("{}", serde_json::to_string_pretty(&event)?),
                             Err(e) => println!("Stream error: {}", e),
                         }
                     }
@@ -517,9 +534,11 @@ async fn main() -> Result<(), Error> {
                 }
                 CollectionSubcommands::Backup(backup_cmd) => {
                     match &backup_cmd.command {
-                        BackupSubcommands::Create { name } => kv.start_backup(name).await?,
+                        BackupSubcommands::Create { name } => {
+                            serde_json::to_value(kv()?.start_backup(name).await?)?
+                        }
                         BackupSubcommands::Status { name, id } => {
-                            kv.backup_status(name, id).await?
+                            serde_json::to_value(kv()?.backup_status(name, id).await?)?
                         }
                         BackupSubcommands::List {
                             name,
@@ -544,14 +563,23 @@ async fn main() -> Result<(), Error> {
                                 }
                             }
 
-                            Value::Array(kv.query_collection(name, builder).await?)
+                            Value::Array(kv()?.query_collection(name, builder).await?)
                         }
                         BackupSubcommands::Upload { name, file } => {
-                            let file_bytes = tokio::fs::read(file).await.map_err(|e| {
-                                Error::BadRequest(format!("Failed to read file: {}", e))
-                            })?;
-
-                            kv.upload_backup(name, file_bytes).await?
+                            let open = || async {
+                                tokio::fs::File::open(file).await.map_err(|e| {
+                                    Error::InvalidInput(format!("Failed to open file: {}", e))
+                                })
+                            };
+                            let checksum = Checksum::of_reader(open().await?).await?;
+                            let file = open().await?;
+                            let len = file.metadata().await?.len();
+
+                            let job = kv()?
+                                .upload_backup_from(name, file, len, Some(checksum), print_progress)
+                                .await?;
+                            eprintln!();
+                            serde_json::to_value(job)?
                         }
                         BackupSubcommands::Download { name, id, output } => {
                             let output_path = output
@@ -559,40 +587,75 @@ async fn main() -> Result<(), Error> {
                                 .unwrap_or_else(|| format!("{}-{}.sst", name, id));
 
                             println!("Downloading backup to {}...", output_path);
-                            let bytes = kv.download_backup(name, id).await?;
-
-                            tokio::fs::write(&output_path, bytes).await.map_err(|e| {
-                                Error::BadRequest(format!("Failed to write file: {}", e))
-                            })?;
-
-                            json!({"message": format!("Backup downloaded successfully to {}", output_path)})
+                            let report = kv()?
+                                .download_backup_to_file(name, id, &output_path, print_progress)
+                                .await?;
+                            eprintln!();
+
+                            json!({
+                                "message": format!("Backup downloaded successfully to {}", output_path),
+                                "size": report.size,
+                                "sha256": report.checksum.to_hex(),
+                                "verified": report.verified,
+                            })
+                        }
+                        BackupSubcommands::Verify { file } => {
+                            let checksum = verify_backup_file(file).await?;
+                            json!({"file": file, "sha256": checksum.to_hex(), "valid": true})
                         }
                     }
                 }
                 CollectionSubcommands::Restore(restore_cmd) => match &restore_cmd.command {
-                    RestoreSubcommands::Create { name, id } => kv.start_restore(name, id).await?,
-                    RestoreSubcommands::Status { name, id } => kv.restore_status(name, id).await?,
+                    RestoreSubcommands::Create { name, id } => {
+                        serde_json::to_value(kv()?.start_restore(name, id).await?)?
+                    }
+                    RestoreSubcommands::Status { name, id } => {
+                        serde_json::to_value(kv()?.restore_status(name, id).await?)?
+                    }
                 },
             }
         }
 
-        Commands::Put { path, value } => {
+        Commands::Put {
+            path,
+            value,
+            if_absent,
+            if_match,
+        } => {
             let (collection, key) = parse_key_path(path)?;
 
             let parsed_value: Value = serde_json::from_str(value)
-                .map_err(|e| Error::BadRequest(format!("Invalid JSON value: {}", e)))?;
+                .map_err(|e| Error::InvalidInput(format!("Invalid JSON value: {}", e)))?;
 
-            kv.put(&collection, &key, &parsed_value).await?
+            let result = match if_match {
+                Some(version) => {
+                    kv()?
+                        .put_if_match(&collection, &key, &parsed_value, version)
+                        .await?
+                }
+                None if *if_absent => {
+                    kv()?
+                        .put_if_absent(&collection, &key, &parsed_value)
+                        .await?
+                }
+                None => kv()?.put(&collection, &key, &parsed_value).await?,
+            };
+            serde_json::to_value(result)?
         }
 
-        Commands::Get { path } => {
+        Commands::Get { path, with_version } => {
             let (collection, key) = parse_key_path(path)?;
-            kv.get(&collection, &key).await?
+            if *with_version {
+                let versioned = kv()?.get_with_version::<Value>(&collection, &key).await?;
+                json!({"value": versioned.value, "version": versioned.version})
+            } else {
+                kv()?.get(&collection, &key).await?
+            }
         }
 
         Commands::Del { path } => {
             let (collection, key) = parse_key_path(path)?;
-            let deleted = kv.delete(&collection, &key).await?;
+            let deleted = kv()?.delete(&collection, &key).await?;
             json!({"path": path, "deleted": deleted})
         }
         Commands::Import {
@@ -600,12 +663,16 @@ async fn main() -> Result<(), Error> {
             key,
             file,
         } => {
-            let file_bytes = tokio::fs::read(&file)
+            let file = tokio::fs::File::open(&file)
                 .await
-                .map_err(|e| Error::BadRequest(format!("Failed to read file: {}", e)))?;
-
-            kv.import_values(collection, key.clone(), file_bytes)
-                .await?
+                .map_err(|e| Error::InvalidInput(format!("Failed to open file: {}", e)))?;
+            let len = file.metadata().await?.len();
+
+            let report = kv()?
+                .import_values_from(collection, key.clone(), file, len, print_progress)
+                .await?;
+            eprintln!();
+            serde_json::to_value(report)?
         }
     };
 
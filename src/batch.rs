@@ -0,0 +1,342 @@
+use crate::capabilities::Feature;
+use crate::{Error, Result, SmolKv, Target};
+use futures_util::stream::{self, StreamExt};
+use reqwest::Method;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// A key a bulk operation could not handle, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BatchFailure {
+    pub key: String,
+    #[serde(alias = "error")]
+    pub reason: String,
+    /// Whether sending the same item again may succeed.
+    #[serde(default)]
+    pub retryable: bool,
+}
+
+impl BatchFailure {
+    pub(crate) fn from_error(key: impl Into<String>, err: &Error) -> Self {
+        Self {
+            key: key.into(),
+            reason: err.to_string(),
+            retryable: err.is_retryable(),
+        }
+    }
+}
+
+/// Per-key outcome of a bulk write or delete.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BatchReport {
+    #[serde(default, alias = "deleted")]
+    pub succeeded: Vec<String>,
+    #[serde(default, alias = "errors")]
+    pub failed: Vec<BatchFailure>,
+}
+
+impl BatchReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Interprets the server's reply to a bulk write of `keys`. Servers that report
+    /// per-item failures use the [`BatchReport`] shape; any other successful reply means
+    /// every item was stored.
+    pub(crate) fn from_reply(keys: Vec<String>, resp: Value) -> Self {
+        let failed = serde_json::from_value::<BatchReport>(resp)
+            .map(|report| report.failed)
+            .unwrap_or_default();
+        let succeeded = keys
+            .into_iter()
+            .filter(|key| !failed.iter().any(|f| &f.key == key))
+            .collect();
+        BatchReport { succeeded, failed }
+    }
+
+    pub(crate) fn merge(&mut self, other: BatchReport) {
+        self.succeeded.extend(other.succeeded);
+        self.failed.extend(other.failed);
+    }
+}
+
+/// A single write in a mixed [`SmolKv::batch`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op<T> {
+    Put {
+        key: String,
+        value: T,
+    },
+    Delete {
+        key: String,
+    },
+    /// Stores the value only if the key does not exist yet.
+    PutIfAbsent {
+        key: String,
+        value: T,
+    },
+}
+
+impl<T> Op<T> {
+    pub fn put(key: impl Into<String>, value: T) -> Self {
+        Op::Put {
+            key: key.into(),
+            value,
+        }
+    }
+
+    pub fn delete(key: impl Into<String>) -> Self {
+        Op::Delete { key: key.into() }
+    }
+
+    pub fn put_if_absent(key: impl Into<String>, value: T) -> Self {
+        Op::PutIfAbsent {
+            key: key.into(),
+            value,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            Op::Put { key, .. } | Op::Delete { key } | Op::PutIfAbsent { key, .. } => key,
+        }
+    }
+}
+
+/// How a [`SmolKv::batch`] was carried out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// The server applied all operations in a single request.
+    Server,
+    /// The server has no batch endpoint, so each operation was sent on its own, in order.
+    Emulated,
+}
+
+/// Result of [`SmolKv::batch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchOutcome {
+    pub mode: BatchMode,
+    pub report: BatchReport,
+}
+
+/// Result of [`SmolKv::batch_get`]. Missing keys map to `None`; keys that could not
+/// be fetched are listed in `failed` instead.
+#[derive(Debug, Clone)]
+pub struct BatchGetReport<T> {
+    pub values: HashMap<String, Option<T>>,
+    pub failed: Vec<BatchFailure>,
+}
+
+impl<T> Default for BatchGetReport<T> {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+            failed: Vec::new(),
+        }
+    }
+}
+
+impl SmolKv {
+    /// Fetches many keys at once, using the server's bulk endpoint when available and a
+    /// bounded number of concurrent single requests otherwise.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.batch_get", skip_all)
+    )]
+    pub async fn batch_get<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        keys: &[impl AsRef<str>],
+    ) -> Result<BatchGetReport<T>> {
+        let keys: Vec<&str> = keys.iter().map(AsRef::as_ref).collect();
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_batch(collection, "batch_get", keys.len());
+
+        if self.capabilities.supports(Feature::BatchGet) != Some(false) {
+            let req = self
+                .request(Method::POST, self.url(&[collection, "_batch", "get"])?)
+                .json(&json!({ "keys": keys }));
+            let result = self
+                .fetch::<HashMap<String, Value>>(req, Target::collection("batch_get", collection));
+            match result.await {
+                Ok(found) => {
+                    self.capabilities.observe(Feature::BatchGet, Ok(()));
+                    return decode_batch_get(&keys, found);
+                }
+                Err(e) if self.capabilities.observe(Feature::BatchGet, Err(&e)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let results = stream::iter(keys)
+            .map(|key| async move { (key, self.get::<T>(collection, key).await) })
+            .buffer_unordered(self.batch_concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut report = BatchGetReport::default();
+        for (key, result) in results {
+            match result {
+                Ok(value) => {
+                    report.values.insert(key.to_string(), Some(value));
+                }
+                Err(Error::NotFound(_)) => {
+                    report.values.insert(key.to_string(), None);
+                }
+                Err(e) => report.failed.push(BatchFailure::from_error(key, &e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Deletes many keys at once, using the server's bulk endpoint when available and a
+    /// bounded number of concurrent single requests otherwise. Keys that did not exist
+    /// count as deleted.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.batch_delete", skip_all)
+    )]
+    pub async fn batch_delete(
+        &self,
+        collection: &str,
+        keys: &[impl AsRef<str>],
+    ) -> Result<BatchReport> {
+        let keys: Vec<&str> = keys.iter().map(AsRef::as_ref).collect();
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_batch(collection, "batch_delete", keys.len());
+
+        if self.capabilities.supports(Feature::BatchDelete) != Some(false) {
+            let req = self
+                .request(Method::POST, self.url(&[collection, "_batch", "delete"])?)
+                .json(&json!({ "keys": keys }));
+            match self
+                .fetch::<Value>(req, Target::collection("batch_delete", collection))
+                .await
+            {
+                Ok(resp) => {
+                    self.capabilities.observe(Feature::BatchDelete, Ok(()));
+                    let keys = keys.iter().map(|key| key.to_string()).collect();
+                    return Ok(BatchReport::from_reply(keys, resp));
+                }
+                Err(e) if self.capabilities.observe(Feature::BatchDelete, Err(&e)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Collected first so the stream does not hold the closure, which would keep the
+        // future from being `Send` when spawned.
+        let requests: Vec<_> = keys
+            .into_iter()
+            .map(|key| async move { (key, self.delete(collection, key).await) })
+            .collect();
+        let results = stream::iter(requests)
+            .buffer_unordered(self.batch_concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut report = BatchReport::default();
+        for (key, result) in results {
+            match result {
+                Ok(_) => report.succeeded.push(key.to_string()),
+                Err(e) => report.failed.push(BatchFailure::from_error(key, &e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Applies puts and deletes to `collection` in one request. Servers without the
+    /// batch endpoint get the operations one at a time, in order; in that mode the
+    /// batch is not atomic.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.batch", skip_all)
+    )]
+    pub async fn batch<T: Serialize>(
+        &self,
+        collection: &str,
+        ops: &[Op<T>],
+    ) -> Result<BatchOutcome> {
+        #[cfg(feature = "metrics")]
+        self.metrics.record_batch(collection, "batch", ops.len());
+        if self.capabilities.supports(Feature::MixedBatch) != Some(false) {
+            let req = self
+                .request(Method::POST, self.url(&[collection, "_batch", "ops"])?)
+                .json(&json!({ "ops": ops }));
+            match self
+                .fetch::<Value>(
+                    req,
+                    Target::collection("batch", collection).non_idempotent(),
+                )
+                .await
+            {
+                Ok(resp) => {
+                    self.capabilities.observe(Feature::MixedBatch, Ok(()));
+                    let keys = ops.iter().map(|op| op.key().to_string()).collect();
+                    return Ok(BatchOutcome {
+                        mode: BatchMode::Server,
+                        report: BatchReport::from_reply(keys, resp),
+                    });
+                }
+                Err(e) if self.capabilities.observe(Feature::MixedBatch, Err(&e)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut report = BatchReport::default();
+        for op in ops {
+            let key = op.key();
+            let result = match op {
+                Op::Put { value, .. } => self.put(collection, key, value).await.map(|_| ()),
+                Op::Delete { .. } => self.delete(collection, key).await.map(|_| ()),
+                Op::PutIfAbsent { value, .. } => {
+                    match self.put_if_absent(collection, key, value).await {
+                        Err(Error::Conflict(_)) => {
+                            report.failed.push(BatchFailure {
+                                key: key.to_string(),
+                                reason: "key already exists".to_string(),
+                                retryable: false,
+                            });
+                            continue;
+                        }
+                        result => result.map(|_| ()),
+                    }
+                }
+            };
+            match result {
+                Ok(()) => report.succeeded.push(key.to_string()),
+                Err(e) => report.failed.push(BatchFailure::from_error(key, &e)),
+            }
+        }
+        Ok(BatchOutcome {
+            mode: BatchMode::Emulated,
+            report,
+        })
+    }
+}
+
+fn decode_batch_get<T: DeserializeOwned>(
+    keys: &[&str],
+    mut found: HashMap<String, Value>,
+) -> Result<BatchGetReport<T>> {
+    let mut report = BatchGetReport::default();
+    for key in keys {
+        let value = match found.remove(*key) {
+            None | Some(Value::Null) => None,
+            Some(value) => match serde_json::from_value(value) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    report
+                        .failed
+                        .push(BatchFailure::from_error(*key, &Error::from(e)));
+                    continue;
+                }
+            },
+        };
+        report.values.insert(key.to_string(), value);
+    }
+    Ok(report)
+}
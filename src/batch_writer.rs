@@ -0,0 +1,277 @@
+use crate::batch::{BatchFailure, BatchReport};
+use crate::{BatchOperation, Error, Result, SmolKv, Target};
+use bytes::Bytes;
+use futures_util::stream::{self, Stream, StreamExt};
+use reqwest::{header::CONTENT_TYPE, Method};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+
+const DEFAULT_MAX_ITEMS: usize = 500;
+const DEFAULT_MAX_BYTES: usize = 4 * 1024 * 1024;
+const DEFAULT_PARALLELISM: usize = 4;
+const DEFAULT_CHUNK_RETRIES: u32 = 2;
+
+/// Writes large numbers of items through the `_batch` endpoint, split into chunks
+/// that stay under the server's body limits.
+///
+/// Created with [`SmolKv::batch_writer`].
+pub struct BatchWriter<T> {
+    kv: SmolKv,
+    collection: String,
+    max_items: usize,
+    max_bytes: usize,
+    parallelism: usize,
+    chunk_retries: u32,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> Clone for BatchWriter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            kv: self.kv.clone(),
+            collection: self.collection.clone(),
+            max_items: self.max_items,
+            max_bytes: self.max_bytes,
+            parallelism: self.parallelism,
+            chunk_retries: self.chunk_retries,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for BatchWriter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchWriter")
+            .field("collection", &self.collection)
+            .field("max_items", &self.max_items)
+            .field("max_bytes", &self.max_bytes)
+            .field("parallelism", &self.parallelism)
+            .field("chunk_retries", &self.chunk_retries)
+            .finish()
+    }
+}
+
+/// Serialized items waiting to be sent together.
+#[derive(Debug, Default)]
+struct Chunk {
+    keys: Vec<String>,
+    items: Vec<Vec<u8>>,
+    bytes: usize,
+}
+
+impl Chunk {
+    fn push(&mut self, key: String, item: Vec<u8>) {
+        // Account for the separating comma.
+        self.bytes += item.len() + 1;
+        self.keys.push(key);
+        self.items.push(item);
+    }
+
+    fn body(&self) -> Bytes {
+        let mut body = Vec::with_capacity(self.bytes + 2);
+        body.push(b'[');
+        for (i, item) in self.items.iter().enumerate() {
+            if i > 0 {
+                body.push(b',');
+            }
+            body.extend_from_slice(item);
+        }
+        body.push(b']');
+        body.into()
+    }
+
+    fn split(mut self) -> (Chunk, Chunk) {
+        let at = self.items.len() / 2;
+        let mut tail = Chunk::default();
+        for (key, item) in self
+            .keys
+            .split_off(at)
+            .into_iter()
+            .zip(self.items.split_off(at))
+        {
+            tail.push(key, item);
+        }
+        let mut head = Chunk::default();
+        for (key, item) in self.keys.into_iter().zip(self.items) {
+            head.push(key, item);
+        }
+        (head, tail)
+    }
+
+    fn fail(self, err: &Error) -> BatchReport {
+        BatchReport {
+            succeeded: Vec::new(),
+            failed: self
+                .keys
+                .into_iter()
+                .map(|key| BatchFailure::from_error(key, err))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Serialize> BatchWriter<T> {
+    /// Maximum number of items per request.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items.max(1);
+        self
+    }
+
+    /// Maximum serialized size of a request body in bytes.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Number of chunks sent concurrently.
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// How many times a failed chunk is sent again, on top of the client's retry policy.
+    /// Each retry waits as long as the retry policy would, honoring `Retry-After`.
+    pub fn chunk_retries(mut self, retries: u32) -> Self {
+        self.chunk_retries = retries;
+        self
+    }
+
+    pub async fn write(
+        &self,
+        items: impl IntoIterator<Item = BatchOperation<T>>,
+    ) -> Result<BatchReport> {
+        self.write_stream(stream::iter(items)).await
+    }
+
+    /// Writes items as they arrive, without collecting the whole input first.
+    pub async fn write_stream(
+        &self,
+        items: impl Stream<Item = BatchOperation<T>>,
+    ) -> Result<BatchReport> {
+        let mut report = BatchReport::default();
+        let mut chunks = Box::pin(self.chunks(items, &mut report))
+            .map(|chunk| self.send_chunk(chunk))
+            .buffer_unordered(self.parallelism);
+
+        let mut sent = BatchReport::default();
+        while let Some(chunk_report) = chunks.next().await {
+            sent.merge(chunk_report);
+        }
+        drop(chunks);
+
+        report.merge(sent);
+        Ok(report)
+    }
+
+    /// Groups serialized items into chunks. Items that cannot be sent at all are
+    /// recorded in `rejected`.
+    fn chunks<'a>(
+        &'a self,
+        items: impl Stream<Item = BatchOperation<T>> + 'a,
+        rejected: &'a mut BatchReport,
+    ) -> impl Stream<Item = Chunk> + 'a {
+        let items = Box::pin(items);
+        stream::unfold(
+            (items, Chunk::default(), rejected, false),
+            move |(mut items, mut current, rejected, done)| async move {
+                if done {
+                    return None;
+                }
+                loop {
+                    let Some(op) = items.next().await else {
+                        return if current.items.is_empty() {
+                            None
+                        } else {
+                            Some((current, (items, Chunk::default(), rejected, true)))
+                        };
+                    };
+
+                    let item = match serde_json::to_vec(&op) {
+                        Ok(item) => item,
+                        Err(e) => {
+                            let err = Error::from(e);
+                            rejected.failed.push(BatchFailure::from_error(op.key, &err));
+                            continue;
+                        }
+                    };
+                    // Two bytes for the surrounding brackets.
+                    if item.len() + 2 > self.max_bytes {
+                        rejected.failed.push(BatchFailure {
+                            key: op.key,
+                            reason: format!(
+                                "item is {} bytes, larger than the {} byte batch limit",
+                                item.len(),
+                                self.max_bytes
+                            ),
+                            retryable: false,
+                        });
+                        continue;
+                    }
+
+                    let full = !current.items.is_empty()
+                        && (current.items.len() >= self.max_items
+                            || current.bytes + item.len() + 2 > self.max_bytes);
+                    if full {
+                        let mut next = Chunk::default();
+                        next.push(op.key, item);
+                        return Some((current, (items, next, rejected, false)));
+                    }
+                    current.push(op.key, item);
+                }
+            },
+        )
+    }
+
+    async fn send_chunk(&self, chunk: Chunk) -> BatchReport {
+        let mut report = BatchReport::default();
+        let mut queue = VecDeque::from([(chunk, 0)]);
+
+        while let Some((chunk, attempt)) = queue.pop_front() {
+            #[cfg(feature = "metrics")]
+            self.kv
+                .metrics
+                .record_batch(&self.collection, "batch_put", chunk.items.len());
+            match self.kv.put_batch_body(&self.collection, chunk.body()).await {
+                Ok(resp) => report.merge(BatchReport::from_reply(chunk.keys, resp)),
+                Err(Error::PayloadTooLarge(_)) if chunk.items.len() > 1 => {
+                    let (head, tail) = chunk.split();
+                    queue.push_back((head, attempt));
+                    queue.push_back((tail, attempt));
+                }
+                Err(e) if e.is_retryable() && attempt < self.chunk_retries => {
+                    tokio::time::sleep(self.kv.retry.delay(attempt + 1, &e)).await;
+                    queue.push_back((chunk, attempt + 1));
+                }
+                Err(e) => report.merge(chunk.fail(&e)),
+            }
+        }
+        report
+    }
+}
+
+impl SmolKv {
+    /// Returns a [`BatchWriter`] for chunked bulk writes to `collection`.
+    pub fn batch_writer<T: Serialize>(&self, collection: &str) -> BatchWriter<T> {
+        BatchWriter {
+            kv: self.clone(),
+            collection: collection.to_string(),
+            max_items: DEFAULT_MAX_ITEMS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            parallelism: DEFAULT_PARALLELISM,
+            chunk_retries: DEFAULT_CHUNK_RETRIES,
+            _marker: PhantomData,
+        }
+    }
+
+    async fn put_batch_body(&self, collection: &str, body: Bytes) -> Result<Value> {
+        let req = self
+            .request(Method::PUT, self.url(&[collection, "_batch"])?)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body);
+        self.fetch(req, Target::collection("batch_put", collection))
+            .await
+    }
+}
@@ -0,0 +1,689 @@
+//! Synchronous client for code that does not run an async runtime.
+//!
+//! [`SmolKv`] wraps the async [`crate::SmolKv`] and drives it on a private
+//! single-threaded tokio runtime, so it returns the same error and response types.
+//! Its methods block the calling thread and must not be called from within an async
+//! runtime.
+//!
+//! Every async method has a blocking counterpart, with streams turned into [`Iter`]s,
+//! except for these:
+//!
+//! - [`crate::SmolKv::subscribe`], which hands out the raw response; use
+//!   [`SmolKv::subscribe`] for decoded events instead.
+//! - [`crate::BatchWriter::write_stream`]; [`BatchWriter::write`] takes any iterator,
+//!   which is already lazy.
+//! - [`KvStore`](crate::KvStore) and [`CachedKv`](crate::CachedKv), whose whole point is
+//!   the async trait.
+//!
+//! ```no_run
+//! use smolkv_client::blocking::SmolKv;
+//!
+//! let kv = SmolKv::new("http://localhost:5050", None::<String>)?;
+//! let value: serde_json::Value = kv.get("users", "bob")?;
+//! # Ok::<(), smolkv_client::Error>(())
+//! ```
+use crate::transfer::DownloadDigest;
+use crate::{
+    BackupJob, BatchGetReport, BatchOperation, BatchOutcome, BatchReport, Checksum,
+    CollectionEvent, CollectionInfo, DownloadReport, ImportReport, Op, PutResult, QueryBuilder,
+    RestoreJob, Result, SmolKvBuilder, TransferProgress, Versioned, WaitOptions,
+};
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio::runtime::Runtime;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::io::StreamReader;
+
+/// Size of the chunks read from blocking readers.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Blocking counterpart of [`crate::SmolKv`]. Cloning is cheap and clones share the
+/// runtime.
+#[derive(Clone)]
+pub struct SmolKv {
+    inner: crate::SmolKv,
+    runtime: Arc<Runtime>,
+}
+
+impl fmt::Debug for SmolKv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmolKv")
+            .field("endpoint", &self.inner.endpoint.as_str())
+            .finish()
+    }
+}
+
+impl SmolKvBuilder {
+    /// Builds a [`blocking::SmolKv`](SmolKv).
+    pub fn build_blocking(self) -> Result<SmolKv> {
+        SmolKv::from_async(self.build()?)
+    }
+}
+
+impl SmolKv {
+    pub fn new(endpoint: impl Into<String>, secret: Option<impl Into<String>>) -> Result<Self> {
+        Self::from_async(crate::SmolKv::new(endpoint, secret)?)
+    }
+
+    pub fn builder(endpoint: impl Into<String>) -> SmolKvBuilder {
+        SmolKvBuilder::new(endpoint)
+    }
+
+    /// Wraps an async client, starting the runtime it will be driven on.
+    pub fn from_async(inner: crate::SmolKv) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The underlying async client.
+    pub fn as_async(&self) -> &crate::SmolKv {
+        &self.inner
+    }
+
+    fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    fn iter<T>(&self, stream: BoxStream<'static, Result<T>>) -> Iter<T> {
+        Iter::new(stream, &self.runtime)
+    }
+
+    /// Reads `reader` on the runtime's blocking pool and hands the chunks to the runtime,
+    /// so slow reads never stall it.
+    fn read_in_background<R: Read + Send + 'static>(
+        &self,
+        mut reader: R,
+    ) -> impl AsyncRead + Send + Sync + 'static {
+        let (tx, rx) = mpsc::channel(4);
+        self.runtime.spawn_blocking(move || {
+            let mut buf = vec![0; READ_CHUNK_SIZE];
+            loop {
+                let chunk = match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => Ok(Bytes::copy_from_slice(&buf[..n])),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => Err(e),
+                };
+                let failed = chunk.is_err();
+                if tx.blocking_send(chunk).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        StreamReader::new(stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        }))
+    }
+
+    /// Returns a typed handle to the `name` collection.
+    pub fn collection<T>(&self, name: &str) -> Collection<T> {
+        Collection {
+            inner: self.inner.collection(name),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    // collection operations
+    pub fn collection_exists(&self, name: &str) -> Result<bool> {
+        self.block_on(self.inner.collection_exists(name))
+    }
+
+    pub fn create_collection(&self, name: &str) -> Result<CollectionInfo> {
+        self.block_on(self.inner.create_collection(name))
+    }
+
+    pub fn drop_collection(&self, name: &str) -> Result<CollectionInfo> {
+        self.block_on(self.inner.drop_collection(name))
+    }
+
+    pub fn list_collection(&self, name: &str, query: QueryBuilder) -> Result<Vec<Value>> {
+        self.block_on(self.inner.list_collection(name, query))
+    }
+
+    pub fn query_collection(&self, name: &str, query: QueryBuilder) -> Result<Vec<Value>> {
+        self.block_on(self.inner.query_collection(name, query))
+    }
+
+    /// See [`crate::SmolKv::query_stream`].
+    pub fn query_iter<T: DeserializeOwned + Send + 'static>(
+        &self,
+        collection: &str,
+        query: QueryBuilder,
+        page_size: usize,
+    ) -> Iter<(String, T)> {
+        self.iter(self.inner.query_stream(collection, query, page_size))
+    }
+
+    // key operations
+    pub fn get<T: DeserializeOwned>(&self, collection: &str, key: &str) -> Result<T> {
+        self.block_on(self.inner.get(collection, key))
+    }
+
+    pub fn put<T: Serialize>(&self, collection: &str, key: &str, value: &T) -> Result<PutResult> {
+        self.block_on(self.inner.put(collection, key, value))
+    }
+
+    pub fn delete(&self, collection: &str, key: &str) -> Result<bool> {
+        self.block_on(self.inner.delete(collection, key))
+    }
+
+    pub fn exists(&self, collection: &str, key: &str) -> Result<bool> {
+        self.block_on(self.inner.exists(collection, key))
+    }
+
+    pub fn import_values(
+        &self,
+        collection: &str,
+        key: Option<String>,
+        values: Vec<u8>,
+    ) -> Result<ImportReport> {
+        self.block_on(self.inner.import_values(collection, key, values))
+    }
+
+    /// See [`crate::SmolKv::import_values_from`]. The reader is read on a background
+    /// thread.
+    pub fn import_values_from<R: Read + Send + 'static>(
+        &self,
+        collection: &str,
+        key: Option<String>,
+        reader: R,
+        len: u64,
+        progress: impl Fn(TransferProgress) + Send + Sync + 'static,
+    ) -> Result<ImportReport> {
+        let reader = self.read_in_background(reader);
+        self.block_on(
+            self.inner
+                .import_values_from(collection, key, reader, len, progress),
+        )
+    }
+
+    pub fn get_with_version<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        key: &str,
+    ) -> Result<Versioned<T>> {
+        self.block_on(self.inner.get_with_version(collection, key))
+    }
+
+    pub fn put_if_absent<T: Serialize>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+    ) -> Result<PutResult> {
+        self.block_on(self.inner.put_if_absent(collection, key, value))
+    }
+
+    pub fn put_if_match<T: Serialize>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+        version: &str,
+    ) -> Result<PutResult> {
+        self.block_on(self.inner.put_if_match(collection, key, value, version))
+    }
+
+    /// See [`crate::SmolKv::update`].
+    pub fn update<T, F>(&self, collection: &str, key: &str, f: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnMut(Option<T>) -> T,
+    {
+        self.block_on(self.inner.update(collection, key, f))
+    }
+
+    pub fn put_with_ttl<T: Serialize>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<PutResult> {
+        self.block_on(self.inner.put_with_ttl(collection, key, value, ttl))
+    }
+
+    pub fn ttl(&self, collection: &str, key: &str) -> Result<Option<Duration>> {
+        self.block_on(self.inner.ttl(collection, key))
+    }
+
+    /// See [`crate::SmolKv::expiry_sweeper`].
+    pub fn expiry_sweeper(&self, collection: &str) -> ExpirySweeper {
+        ExpirySweeper {
+            inner: self.inner.expiry_sweeper(collection),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    // batch operations
+    pub fn batch_put<T: Serialize>(
+        &self,
+        collection: &str,
+        items: &[BatchOperation<T>],
+    ) -> Result<()> {
+        self.block_on(self.inner.batch_put(collection, items))
+    }
+
+    pub fn batch_get<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        keys: &[impl AsRef<str>],
+    ) -> Result<BatchGetReport<T>> {
+        self.block_on(self.inner.batch_get(collection, keys))
+    }
+
+    pub fn batch_delete(&self, collection: &str, keys: &[impl AsRef<str>]) -> Result<BatchReport> {
+        self.block_on(self.inner.batch_delete(collection, keys))
+    }
+
+    /// See [`crate::SmolKv::batch`].
+    pub fn batch<T: Serialize>(&self, collection: &str, ops: &[Op<T>]) -> Result<BatchOutcome> {
+        self.block_on(self.inner.batch(collection, ops))
+    }
+
+    /// See [`crate::SmolKv::batch_writer`].
+    pub fn batch_writer<T: Serialize>(&self, collection: &str) -> BatchWriter<T> {
+        BatchWriter {
+            inner: self.inner.batch_writer(collection),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Change feed as a blocking iterator, reconnecting like
+    /// [`crate::SmolKv::subscribe_events`]. Each call to `next` waits for the next event.
+    pub fn subscribe(&self, collection: &str) -> Iter<CollectionEvent> {
+        self.iter(self.inner.subscribe_events(collection))
+    }
+
+    // backup and restore
+    pub fn start_backup(&self, collection: &str) -> Result<BackupJob> {
+        self.block_on(self.inner.start_backup(collection))
+    }
+
+    pub fn backup_status(&self, collection: &str, id: &str) -> Result<BackupJob> {
+        self.block_on(self.inner.backup_status(collection, id))
+    }
+
+    pub fn wait_for_backup(
+        &self,
+        collection: &str,
+        id: &str,
+        opts: WaitOptions,
+    ) -> Result<BackupJob> {
+        self.block_on(self.inner.wait_for_backup(collection, id, opts))
+    }
+
+    pub fn backup_and_download(&self, collection: &str) -> Result<bytes::Bytes> {
+        self.block_on(self.inner.backup_and_download(collection))
+    }
+
+    pub fn download_backup(&self, collection: &str, backup_id: &str) -> Result<bytes::Bytes> {
+        self.block_on(self.inner.download_backup(collection, backup_id))
+    }
+
+    /// Backup download as a blocking iterator of chunks. See
+    /// [`crate::SmolKv::download_backup_stream`].
+    pub fn download_backup_iter(
+        &self,
+        collection: &str,
+        backup_id: &str,
+        progress: impl Fn(TransferProgress) + Send + 'static,
+    ) -> Result<Iter<Bytes>> {
+        let stream = self.block_on(
+            self.inner
+                .download_backup_stream(collection, backup_id, progress),
+        )?;
+        Ok(self.iter(stream))
+    }
+
+    /// See [`crate::SmolKv::download_backup_to`]. Chunks are written between polls of
+    /// the runtime rather than from within it.
+    pub fn download_backup_to<W: Write>(
+        &self,
+        collection: &str,
+        backup_id: &str,
+        writer: &mut W,
+        progress: impl Fn(TransferProgress) + Send + 'static,
+    ) -> Result<DownloadReport> {
+        let (resp, expected) = self.block_on(self.inner.open_backup_download(
+            "download_backup_to",
+            collection,
+            backup_id,
+        ))?;
+        let mut digest = DownloadDigest::new(resp.content_length(), expected);
+        let mut chunks = resp.bytes_stream();
+        while let Some(chunk) = self.block_on(chunks.next()) {
+            let chunk = chunk?;
+            writer.write_all(&chunk)?;
+            progress(digest.update(&chunk));
+        }
+        writer.flush()?;
+        digest.finish()
+    }
+
+    /// See [`crate::SmolKv::download_backup_to_file`].
+    pub fn download_backup_to_file(
+        &self,
+        collection: &str,
+        backup_id: &str,
+        path: impl AsRef<Path>,
+        progress: impl Fn(TransferProgress) + Send + 'static,
+    ) -> Result<DownloadReport> {
+        self.block_on(
+            self.inner
+                .download_backup_to_file(collection, backup_id, path, progress),
+        )
+    }
+
+    pub fn upload_backup(&self, collection: &str, backup_data: Vec<u8>) -> Result<BackupJob> {
+        self.block_on(self.inner.upload_backup(collection, backup_data))
+    }
+
+    /// See [`crate::SmolKv::upload_backup_from`]. The reader is read on a background
+    /// thread.
+    pub fn upload_backup_from<R: Read + Send + 'static>(
+        &self,
+        collection: &str,
+        reader: R,
+        len: u64,
+        checksum: Option<Checksum>,
+        progress: impl Fn(TransferProgress) + Send + Sync + 'static,
+    ) -> Result<BackupJob> {
+        let reader = self.read_in_background(reader);
+        self.block_on(
+            self.inner
+                .upload_backup_from(collection, reader, len, checksum, progress),
+        )
+    }
+
+    pub fn start_restore(&self, collection: &str, id: &str) -> Result<RestoreJob> {
+        self.block_on(self.inner.start_restore(collection, id))
+    }
+
+    pub fn restore_status(&self, collection: &str, id: &str) -> Result<RestoreJob> {
+        self.block_on(self.inner.restore_status(collection, id))
+    }
+
+    pub fn wait_for_restore(
+        &self,
+        collection: &str,
+        id: &str,
+        opts: WaitOptions,
+    ) -> Result<RestoreJob> {
+        self.block_on(self.inner.wait_for_restore(collection, id, opts))
+    }
+}
+
+/// Blocking counterpart of [`crate::Collection`], created with [`SmolKv::collection`].
+pub struct Collection<T> {
+    inner: crate::Collection<T>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> Clone for Collection<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            runtime: self.runtime.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Collection<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<T> Collection<T> {
+    pub fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    pub fn delete(&self, key: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.delete(key))
+    }
+
+    pub fn exists(&self, key: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.exists(key))
+    }
+}
+
+impl<T: DeserializeOwned> Collection<T> {
+    pub fn get(&self, key: &str) -> Result<T> {
+        self.runtime.block_on(self.inner.get(key))
+    }
+
+    pub fn get_with_version(&self, key: &str) -> Result<Versioned<T>> {
+        self.runtime.block_on(self.inner.get_with_version(key))
+    }
+
+    pub fn query(&self, query: QueryBuilder) -> Result<Vec<T>> {
+        self.runtime.block_on(self.inner.query(query))
+    }
+
+    pub fn list(&self, query: QueryBuilder) -> Result<Vec<T>> {
+        self.runtime.block_on(self.inner.list(query))
+    }
+}
+
+impl<T: Serialize> Collection<T> {
+    pub fn put(&self, key: &str, value: &T) -> Result<PutResult> {
+        self.runtime.block_on(self.inner.put(key, value))
+    }
+
+    pub fn put_if_absent(&self, key: &str, value: &T) -> Result<PutResult> {
+        self.runtime.block_on(self.inner.put_if_absent(key, value))
+    }
+
+    pub fn put_if_match(&self, key: &str, value: &T, version: &str) -> Result<PutResult> {
+        self.runtime
+            .block_on(self.inner.put_if_match(key, value, version))
+    }
+
+    pub fn batch_put(&self, items: &[BatchOperation<T>]) -> Result<()> {
+        self.runtime.block_on(self.inner.batch_put(items))
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// See [`crate::SmolKv::update`].
+    pub fn update(&self, key: &str, f: impl FnMut(Option<T>) -> T) -> Result<T> {
+        self.runtime.block_on(self.inner.update(key, f))
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Collection<T> {
+    /// See [`crate::Collection::query_stream`].
+    pub fn query_iter(&self, query: QueryBuilder, page_size: usize) -> Iter<(String, T)> {
+        Iter::new(self.inner.query_stream(query, page_size), &self.runtime)
+    }
+
+    /// Change feed with values decoded as `T`. Values are `None` for deletions.
+    pub fn subscribe(&self) -> Iter<CollectionEvent<Option<T>>> {
+        Iter::new(self.inner.subscribe(), &self.runtime)
+    }
+}
+
+/// Blocking counterpart of [`crate::BatchWriter`], created with [`SmolKv::batch_writer`].
+pub struct BatchWriter<T> {
+    inner: crate::BatchWriter<T>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> Clone for BatchWriter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            runtime: self.runtime.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for BatchWriter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<T: Serialize> BatchWriter<T> {
+    /// See [`crate::BatchWriter::max_items`].
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.inner = self.inner.max_items(max_items);
+        self
+    }
+
+    /// See [`crate::BatchWriter::max_bytes`].
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.inner = self.inner.max_bytes(max_bytes);
+        self
+    }
+
+    /// See [`crate::BatchWriter::parallelism`].
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.inner = self.inner.parallelism(parallelism);
+        self
+    }
+
+    /// See [`crate::BatchWriter::chunk_retries`].
+    pub fn chunk_retries(mut self, retries: u32) -> Self {
+        self.inner = self.inner.chunk_retries(retries);
+        self
+    }
+
+    pub fn write(&self, items: impl IntoIterator<Item = BatchOperation<T>>) -> Result<BatchReport> {
+        self.runtime.block_on(self.inner.write(items))
+    }
+}
+
+/// Blocking counterpart of [`crate::ExpirySweeper`], created with
+/// [`SmolKv::expiry_sweeper`].
+#[derive(Clone)]
+pub struct ExpirySweeper {
+    inner: crate::ExpirySweeper,
+    runtime: Arc<Runtime>,
+}
+
+impl fmt::Debug for ExpirySweeper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl ExpirySweeper {
+    /// See [`crate::ExpirySweeper::interval`].
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.interval(interval);
+        self
+    }
+
+    /// See [`crate::ExpirySweeper::page_size`].
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.inner = self.inner.page_size(page_size);
+        self
+    }
+
+    pub fn put_with_ttl<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<PutResult> {
+        self.runtime
+            .block_on(self.inner.put_with_ttl(key, value, ttl))
+    }
+
+    /// See [`crate::ExpirySweeper::get`].
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        self.runtime.block_on(self.inner.get(key))
+    }
+
+    /// See [`crate::ExpirySweeper::ttl`].
+    pub fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        self.runtime.block_on(self.inner.ttl(key))
+    }
+
+    /// See [`crate::ExpirySweeper::sweep`].
+    pub fn sweep(&self) -> Result<BatchReport> {
+        self.runtime.block_on(self.inner.sweep())
+    }
+
+    /// Runs [`ExpirySweeper::sweep`] every interval on a background thread until the
+    /// returned handle is dropped. The thread drives the client's runtime, which other
+    /// calls share while it runs.
+    pub fn spawn(self) -> SweeperHandle {
+        let Self { inner, runtime } = self;
+        let (stop, stopped) = oneshot::channel::<()>();
+        let thread = thread::spawn(move || {
+            runtime.block_on(async move {
+                let _sweeper = inner.spawn();
+                let _ = stopped.await;
+            })
+        });
+        SweeperHandle {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Background thread started by [`ExpirySweeper::spawn`]. Dropping it stops the thread
+/// and waits for it to exit.
+#[derive(Debug)]
+pub struct SweeperHandle {
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for SweeperHandle {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Blocking iterator over a stream of results, returned by [`SmolKv::subscribe`],
+/// [`SmolKv::query_iter`] and [`SmolKv::download_backup_iter`].
+pub struct Iter<T> {
+    stream: BoxStream<'static, Result<T>>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> Iter<T> {
+    fn new(stream: BoxStream<'static, Result<T>>, runtime: &Arc<Runtime>) -> Self {
+        Self {
+            stream,
+            runtime: runtime.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Iter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Iter").finish_non_exhaustive()
+    }
+}
+
+impl<T> Iterator for Iter<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{Error, Layer, Result, RetryPolicy, SharedTransport, SmolKv, Transport};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::{Client, Url};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_BATCH_CONCURRENCY: usize = 16;
+
+/// Builder for [`SmolKv`] that validates its configuration instead of panicking.
+#[derive(Default)]
+pub struct SmolKvBuilder {
+    endpoint: String,
+    secret: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    headers: Vec<(String, String)>,
+    client: Option<Client>,
+    transport: Option<SharedTransport>,
+    layers: Vec<Arc<dyn Layer>>,
+    retry: RetryPolicy,
+    batch_concurrency: Option<usize>,
+    #[cfg(feature = "tracing")]
+    redact_keys: bool,
+    #[cfg(feature = "tracing")]
+    trace_context: Option<crate::trace::TraceContext>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<dyn crate::MetricsSink>>,
+}
+
+impl fmt::Debug for SmolKvBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmolKvBuilder")
+            .field("endpoint", &self.endpoint)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("user_agent", &self.user_agent)
+            .field("headers", &self.headers)
+            .field("client", &self.client)
+            .field("transport", &self.transport.is_some())
+            .field("layers", &self.layers.len())
+            .field("retry", &self.retry)
+            .field("batch_concurrency", &self.batch_concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SmolKvBuilder {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    pub fn secret(mut self, secret: Option<impl Into<String>>) -> Self {
+        self.secret = secret.map(Into::into);
+        self
+    }
+
+    /// Total time allowed for each request, including reading the body. Subscriptions
+    /// stay open past it.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Time allowed to establish a connection. Only applies to clients built here.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Adds a header sent with every request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Reuses an existing `reqwest::Client` instead of building a new one.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Sends requests through `transport` instead of the `reqwest::Client`. The client
+    /// is still used to build requests, so the headers set here apply, and the timeout
+    /// is enforced around each call to the transport.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Wraps the transport in `layer`. The first layer added sees requests first.
+    ///
+    /// Layers run below the client's retry policy, so each retry passes through them.
+    pub fn layer(mut self, layer: impl Layer + 'static) -> Self {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    /// Retry policy for failed requests. Idempotent requests are retried by default.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Maximum number of concurrent requests when a bulk operation has to fall back to
+    /// single-key requests. Defaults to 16.
+    pub fn batch_concurrency(mut self, concurrency: usize) -> Self {
+        self.batch_concurrency = Some(concurrency.max(1));
+        self
+    }
+
+    /// Records keys in tracing spans as a short SHA-256 prefix instead of in full.
+    #[cfg(feature = "tracing")]
+    pub fn redact_keys(mut self, redact: bool) -> Self {
+        self.redact_keys = redact;
+        self
+    }
+
+    /// Continues the caller's distributed trace instead of starting a new one for each
+    /// request. `context` returns the W3C `traceparent` of the current context, e.g. one
+    /// injected from OpenTelemetry, or `None` when there is none.
+    #[cfg(feature = "tracing")]
+    pub fn trace_context(
+        mut self,
+        context: impl Fn() -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.trace_context = Some(Arc::new(context));
+        self
+    }
+
+    /// Sends client metrics to `sink` instead of the `metrics` crate facade.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_sink(mut self, sink: impl crate::MetricsSink + 'static) -> Self {
+        self.metrics = Some(Arc::new(sink));
+        self
+    }
+
+    pub fn build(self) -> Result<SmolKv> {
+        let endpoint = normalize_endpoint(&self.endpoint)?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::Config(format!("invalid header name '{name}': {e}")))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| Error::Config(format!("invalid value for header '{name}': {e}")))?;
+            headers.append(name, value);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            let value = HeaderValue::from_str(user_agent)
+                .map_err(|e| Error::Config(format!("invalid user agent: {e}")))?;
+            headers.insert(USER_AGENT, value);
+        }
+        if let Some(secret) = &self.secret {
+            let mut value = HeaderValue::from_str(secret)
+                .map_err(|_| Error::Config("secret key contains invalid characters".into()))?;
+            value.set_sensitive(true);
+            headers.insert("X-SECRET-KEY", value);
+        }
+
+        let client = match self.client {
+            Some(_) if self.connect_timeout.is_some() => {
+                return Err(Error::Config(
+                    "connect timeout cannot be applied to a provided client".into(),
+                ))
+            }
+            Some(client) => client,
+            None => {
+                let mut builder = Client::builder();
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                builder
+                    .build()
+                    .map_err(|e| Error::Config(format!("failed to build http client: {e}")))?
+            }
+        };
+
+        let base = self
+            .transport
+            .unwrap_or_else(|| Arc::new(client.clone()) as SharedTransport);
+        let transport = self
+            .layers
+            .iter()
+            .rev()
+            .fold(base, |inner, layer| layer.layer(inner));
+
+        Ok(SmolKv {
+            endpoint,
+            client,
+            transport,
+            headers,
+            timeout: self.timeout,
+            retry: self.retry,
+            batch_concurrency: self.batch_concurrency.unwrap_or(DEFAULT_BATCH_CONCURRENCY),
+            capabilities: Default::default(),
+            #[cfg(feature = "tracing")]
+            redact_keys: self.redact_keys,
+            #[cfg(feature = "tracing")]
+            trace_context: self.trace_context,
+            #[cfg(feature = "metrics")]
+            metrics: self
+                .metrics
+                .unwrap_or_else(|| Arc::new(crate::MetricsFacade)),
+        })
+    }
+}
+
+fn normalize_endpoint(endpoint: &str) -> Result<Url> {
+    let mut url = Url::parse(endpoint.trim())
+        .map_err(|e| Error::Config(format!("invalid endpoint '{endpoint}': {e}")))?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(Error::Config(format!(
+            "unsupported endpoint scheme '{}'",
+            url.scheme()
+        )));
+    }
+    if url.host_str().is_none() {
+        return Err(Error::Config(format!("endpoint '{endpoint}' has no host")));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(Error::Config(format!(
+            "endpoint '{endpoint}' must not contain a query or fragment"
+        )));
+    }
+
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(&path);
+    Ok(url)
+}
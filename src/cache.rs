@@ -0,0 +1,521 @@
+use crate::memory::not_found;
+use crate::{
+    BatchOperation, CollectionEvent, Error, EventStream, KvStore, PutResult, QueryBuilder, Result,
+    SmolKv,
+};
+use futures_util::stream::StreamExt;
+use reqwest::Method;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+const DEFAULT_MAX_ENTRIES: usize = 1024;
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// Counters of a [`CachedKv`], shared by its clones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to stay under the size limit.
+    pub evictions: u64,
+    /// Entries dropped because the change feed reported a write.
+    pub invalidations: u64,
+}
+
+/// What is known about a key.
+#[derive(Debug)]
+enum Cached {
+    Value(Value),
+    /// The key exists but only [`KvStore::exists`] was asked.
+    Exists,
+    Missing,
+}
+
+#[derive(Debug)]
+struct Entry {
+    state: Cached,
+    stored_at: Instant,
+    tick: u64,
+}
+
+/// The entries of one collection, in least recently used order.
+#[derive(Debug, Default)]
+struct CollectionCache {
+    entries: HashMap<String, Entry>,
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    /// Bumped on every write and invalidation so reads and writes that started before
+    /// one don't store what they fetched or sent.
+    generation: u64,
+    /// Set when the change feed ended; nothing is cached until it is reopened.
+    closed_at: Option<Instant>,
+}
+
+impl CollectionCache {
+    fn lookup(&mut self, key: &str, ttl: Duration) -> Option<&Cached> {
+        if self.closed_at.is_some() {
+            return None;
+        }
+        let entry = self.entries.get(key)?;
+        if entry.stored_at.elapsed() >= ttl {
+            self.remove(key);
+            return None;
+        }
+
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.recency.remove(&entry.tick);
+        entry.tick = self.tick;
+        self.recency.insert(self.tick, key.to_string());
+        Some(&entry.state)
+    }
+
+    /// Stores what a read fetched, unless the key was written since `generation`.
+    /// Returns the number of evicted entries.
+    fn fill(&mut self, key: &str, state: Cached, generation: u64, max_entries: usize) -> u64 {
+        match generation == self.generation {
+            true => self.insert(key, state, max_entries),
+            false => 0,
+        }
+    }
+
+    /// Stores what this client wrote, unless a change was applied since `generation`,
+    /// which may be newer than the write. Returns the number of evicted entries.
+    fn write(&mut self, key: &str, state: Cached, generation: u64, max_entries: usize) -> u64 {
+        let unchanged = generation == self.generation;
+        self.generation += 1;
+        if unchanged {
+            self.insert(key, state, max_entries)
+        } else {
+            self.remove(key);
+            0
+        }
+    }
+
+    fn insert(&mut self, key: &str, state: Cached, max_entries: usize) -> u64 {
+        if self.closed_at.is_some() {
+            return 0;
+        }
+        self.remove(key);
+        self.tick += 1;
+        let entry = Entry {
+            state,
+            stored_at: Instant::now(),
+            tick: self.tick,
+        };
+        self.entries.insert(key.to_string(), entry);
+        self.recency.insert(self.tick, key.to_string());
+
+        let mut evicted = 0;
+        while self.entries.len() > max_entries {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            evicted += 1;
+        }
+        evicted
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.recency.remove(&entry.tick);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the key an event is about, keeping it only when the cache already holds
+    /// the result, like after the echo of this client's own write.
+    fn apply(&mut self, event: &CollectionEvent) -> bool {
+        self.generation += 1;
+        let unchanged = match (event.operation.as_str(), self.entries.get(&event.key)) {
+            (
+                "put",
+                Some(Entry {
+                    state: Cached::Value(value),
+                    ..
+                }),
+            ) => *value == event.value,
+            (
+                "delete",
+                Some(Entry {
+                    state: Cached::Missing,
+                    ..
+                }),
+            ) => true,
+            _ => false,
+        };
+        !unchanged && self.remove(&event.key)
+    }
+
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+fn lock(cache: &Mutex<CollectionCache>) -> MutexGuard<'_, CollectionCache> {
+    // Nothing panics while holding the lock, and every entry is valid on its own.
+    cache.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A collection's cache and the task feeding it changes.
+struct Subscribed {
+    cache: Arc<Mutex<CollectionCache>>,
+    task: JoinHandle<()>,
+}
+
+struct Shared<S> {
+    store: S,
+    collections: Mutex<HashMap<String, Subscribed>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: Arc<AtomicU64>,
+}
+
+impl<S> Drop for Shared<S> {
+    fn drop(&mut self) {
+        let collections = self
+            .collections
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner());
+        for subscribed in collections.values() {
+            subscribed.task.abort();
+        }
+    }
+}
+
+/// A [`KvStore`] that serves `get` and `exists` from memory.
+///
+/// Each collection gets a bounded LRU cache whose entries expire after a TTL. Writes
+/// made through the wrapper update the cache as they reach the store, and a
+/// subscription to each cached collection's change feed drops entries that other
+/// clients overwrite or delete, so caches across instances stay coherent. Writes
+/// made before a collection's subscription is connected, or while it is reconnecting
+/// without replay, are only noticed once the TTL expires. If the change feed ends,
+/// the collection is not cached until it is reopened, at most once per TTL.
+///
+/// Subscriptions run as background tasks, so the cache must be used inside a Tokio
+/// runtime. Cloning is cheap and clones share the same cache; the tasks stop when
+/// the last clone is dropped.
+pub struct CachedKv<S = SmolKv> {
+    shared: Arc<Shared<S>>,
+    max_entries: usize,
+    ttl: Duration,
+}
+
+impl<S> Clone for CachedKv<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            max_entries: self.max_entries,
+            ttl: self.ttl,
+        }
+    }
+}
+
+impl<S: KvStore> fmt::Debug for CachedKv<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedKv")
+            .field("max_entries", &self.max_entries)
+            .field("ttl", &self.ttl)
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: KvStore> CachedKv<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                store,
+                collections: Mutex::new(HashMap::new()),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                evictions: AtomicU64::new(0),
+                invalidations: Arc::new(AtomicU64::new(0)),
+            }),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Maximum number of keys cached per collection. Defaults to 1024.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// How long an entry is served before it is fetched again. Defaults to 60 seconds.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The wrapped store.
+    pub fn store(&self) -> &S {
+        &self.shared.store
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.shared.hits.load(Ordering::Relaxed),
+            misses: self.shared.misses.load(Ordering::Relaxed),
+            evictions: self.shared.evictions.load(Ordering::Relaxed),
+            invalidations: self.shared.invalidations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Drops a cached key, e.g. after writing it without going through the cache.
+    pub fn invalidate(&self, collection: &str, key: &str) {
+        let collections = self.collections();
+        if let Some(subscribed) = collections.get(collection) {
+            let mut cache = lock(&subscribed.cache);
+            cache.generation += 1;
+            cache.remove(key);
+        }
+    }
+
+    /// Drops every cached key of every collection.
+    pub fn clear(&self) {
+        for subscribed in self.collections().values() {
+            lock(&subscribed.cache).clear();
+        }
+    }
+
+    fn collections(&self) -> MutexGuard<'_, HashMap<String, Subscribed>> {
+        self.shared
+            .collections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The cache of `collection`, subscribing to its change feed on first use.
+    fn cache(&self, collection: &str) -> Arc<Mutex<CollectionCache>> {
+        let mut collections = self.collections();
+        if let Some(subscribed) = collections.get(collection) {
+            let closed_at = lock(&subscribed.cache).closed_at;
+            if closed_at.is_none_or(|at| at.elapsed() < self.ttl) {
+                return subscribed.cache.clone();
+            }
+        }
+
+        let cache = Arc::new(Mutex::new(CollectionCache::default()));
+        let events = self.shared.store.subscribe(collection);
+        let task = tokio::spawn(invalidate_from(
+            events,
+            cache.clone(),
+            self.shared.invalidations.clone(),
+        ));
+        let subscribed = Subscribed {
+            cache: cache.clone(),
+            task,
+        };
+        if let Some(previous) = collections.insert(collection.to_string(), subscribed) {
+            previous.task.abort();
+        }
+        cache
+    }
+
+    fn hit(&self) {
+        self.shared.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn miss(&self) {
+        self.shared.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn evicted(&self, count: u64) {
+        self.shared.evictions.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+/// Drops entries as the change feed reports writes. A failed event may stand for
+/// missed changes, so it clears the whole collection.
+async fn invalidate_from(
+    mut events: EventStream,
+    cache: Arc<Mutex<CollectionCache>>,
+    invalidations: Arc<AtomicU64>,
+) {
+    while let Some(event) = events.next().await {
+        let mut cache = lock(&cache);
+        match event {
+            Ok(event) => {
+                if cache.apply(&event) {
+                    invalidations.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Err(_) => cache.clear(),
+        }
+    }
+    let mut cache = lock(&cache);
+    cache.clear();
+    cache.closed_at = Some(Instant::now());
+}
+
+impl<S: KvStore> KvStore for CachedKv<S> {
+    async fn get<T: DeserializeOwned + Send>(&self, collection: &str, key: &str) -> Result<T> {
+        let cache = self.cache(collection);
+        let generation = {
+            let mut cache = lock(&cache);
+            match cache.lookup(key, self.ttl) {
+                Some(Cached::Value(value)) => {
+                    let value = T::deserialize(value);
+                    self.hit();
+                    return Ok(value?);
+                }
+                Some(Cached::Missing) => {
+                    self.hit();
+                    return Err(not_found(Method::GET, collection, Some(key)));
+                }
+                Some(Cached::Exists) | None => cache.generation,
+            }
+        };
+
+        self.miss();
+        let (state, result) = match self.shared.store.get::<Value>(collection, key).await {
+            Ok(value) => {
+                let result = T::deserialize(&value).map_err(Error::from);
+                (Cached::Value(value), result)
+            }
+            Err(e @ Error::NotFound(_)) => (Cached::Missing, Err(e)),
+            Err(e) => return Err(e),
+        };
+        let evicted = lock(&cache).fill(key, state, generation, self.max_entries);
+        self.evicted(evicted);
+        result
+    }
+
+    async fn put<T: Serialize + Sync>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+    ) -> Result<PutResult> {
+        let value = serde_json::to_value(value)?;
+        let cache = self.cache(collection);
+        let generation = lock(&cache).generation;
+        match self.shared.store.put(collection, key, &value).await {
+            Ok(result) => {
+                let evicted =
+                    lock(&cache).write(key, Cached::Value(value), generation, self.max_entries);
+                self.evicted(evicted);
+                Ok(result)
+            }
+            Err(e) => {
+                // The write may have been applied before the error.
+                self.invalidate(collection, key);
+                Err(e)
+            }
+        }
+    }
+
+    async fn delete(&self, collection: &str, key: &str) -> Result<bool> {
+        let cache = self.cache(collection);
+        let generation = lock(&cache).generation;
+        match self.shared.store.delete(collection, key).await {
+            Ok(existed) => {
+                let evicted =
+                    lock(&cache).write(key, Cached::Missing, generation, self.max_entries);
+                self.evicted(evicted);
+                Ok(existed)
+            }
+            Err(e) => {
+                self.invalidate(collection, key);
+                Err(e)
+            }
+        }
+    }
+
+    async fn exists(&self, collection: &str, key: &str) -> Result<bool> {
+        let cache = self.cache(collection);
+        let generation = {
+            let mut cache = lock(&cache);
+            match cache.lookup(key, self.ttl) {
+                Some(Cached::Value(_) | Cached::Exists) => {
+                    self.hit();
+                    return Ok(true);
+                }
+                Some(Cached::Missing) => {
+                    self.hit();
+                    return Ok(false);
+                }
+                None => cache.generation,
+            }
+        };
+
+        self.miss();
+        let exists = self.shared.store.exists(collection, key).await?;
+        let state = match exists {
+            true => Cached::Exists,
+            false => Cached::Missing,
+        };
+        let evicted = lock(&cache).fill(key, state, generation, self.max_entries);
+        self.evicted(evicted);
+        Ok(exists)
+    }
+
+    async fn batch_put<T: Serialize + Sync>(
+        &self,
+        collection: &str,
+        items: &[BatchOperation<T>],
+    ) -> Result<()> {
+        let items = items
+            .iter()
+            .map(|item| {
+                Ok(BatchOperation {
+                    key: item.key.clone(),
+                    value: serde_json::to_value(&item.value)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let cache = self.cache(collection);
+        let generation = lock(&cache).generation;
+        let result = self.shared.store.batch_put(collection, &items).await;
+
+        let mut cache = lock(&cache);
+        let changed = cache.generation != generation;
+        let mut evicted = 0;
+        for item in items {
+            match &result {
+                Ok(()) if !changed => {
+                    let generation = cache.generation;
+                    evicted += cache.write(
+                        &item.key,
+                        Cached::Value(item.value),
+                        generation,
+                        self.max_entries,
+                    );
+                }
+                // A change applied in the meantime may be newer, and some items may have
+                // been stored before an error.
+                _ => {
+                    cache.generation += 1;
+                    cache.remove(&item.key);
+                }
+            }
+        }
+        drop(cache);
+        self.evicted(evicted);
+        result
+    }
+
+    async fn query(&self, collection: &str, query: QueryBuilder) -> Result<Vec<Value>> {
+        self.shared.store.query(collection, query).await
+    }
+
+    fn subscribe(&self, collection: &str) -> EventStream {
+        self.shared.store.subscribe(collection)
+    }
+}
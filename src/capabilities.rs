@@ -0,0 +1,60 @@
+use crate::Error;
+use reqwest::StatusCode;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Optional server endpoints the client probes for and falls back from.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Feature {
+    BatchGet,
+    BatchDelete,
+    MixedBatch,
+}
+
+impl Feature {
+    const COUNT: usize = 3;
+}
+
+const SUPPORTED: u8 = 1;
+const UNSUPPORTED: u8 = 2;
+
+/// What the server is known to support, shared by all clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct Capabilities {
+    flags: [AtomicU8; Feature::COUNT],
+}
+
+impl Capabilities {
+    /// `None` until the feature has been tried once.
+    pub fn supports(&self, feature: Feature) -> Option<bool> {
+        match self.flags[feature as usize].load(Ordering::Relaxed) {
+            SUPPORTED => Some(true),
+            UNSUPPORTED => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn record(&self, feature: Feature, supported: bool) {
+        let flag = if supported { SUPPORTED } else { UNSUPPORTED };
+        self.flags[feature as usize].store(flag, Ordering::Relaxed);
+    }
+
+    /// Records the outcome of a request to an optional endpoint. Returns whether the
+    /// error means the endpoint is missing, in which case the caller should fall back.
+    pub fn observe(&self, feature: Feature, result: Result<(), &Error>) -> bool {
+        match result {
+            Ok(()) => {
+                self.record(feature, true);
+                false
+            }
+            Err(e) => match e.status() {
+                Some(StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED) => {
+                    self.record(feature, false);
+                    true
+                }
+                // The collection itself may be missing, so don't remember this one.
+                Some(StatusCode::NOT_FOUND) => self.supports(feature).is_none(),
+                _ => false,
+            },
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Header carrying the hex encoded SHA-256 of a backup file, in both directions.
+pub const CHECKSUM_HEADER: &str = "X-Checksum-SHA256";
+
+/// SHA-256 digest of a backup file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Checksum([u8; 32]);
+
+impl Checksum {
+    pub fn of(data: &[u8]) -> Self {
+        Self(Sha256::digest(data).into())
+    }
+
+    /// Hashes everything `reader` yields.
+    pub async fn of_reader<R: AsyncRead + Unpin>(mut reader: R) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        Ok(Self(hasher.finalize().into()))
+    }
+
+    pub(crate) fn from_hasher(hasher: Sha256) -> Self {
+        Self(hasher.finalize().into())
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Fails with [`Error::ChecksumMismatch`] unless `self` equals `expected`.
+    pub fn verify(&self, expected: &Checksum) -> Result<()> {
+        if self == expected {
+            Ok(())
+        } else {
+            Err(Error::ChecksumMismatch {
+                expected: expected.to_hex(),
+                actual: self.to_hex(),
+            })
+        }
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut bytes = [0; 32];
+        hex::decode_to_slice(s.trim(), &mut bytes)
+            .map_err(|e| Error::InvalidInput(format!("invalid sha256 checksum '{s}': {e}")))?;
+        Ok(Self(bytes))
+    }
+}
+
+/// Path of the manifest written next to a downloaded backup file, `<path>.sha256`, e.g.
+/// `users-42.sst.sha256` for a backup saved as `users-42.sst`.
+pub fn manifest_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut manifest = path.as_ref().as_os_str().to_owned();
+    manifest.push(".sha256");
+    PathBuf::from(manifest)
+}
+
+/// Writes a `sha256sum` compatible manifest describing `file` to `manifest`.
+pub async fn write_manifest(
+    manifest: impl AsRef<Path>,
+    file: impl AsRef<Path>,
+    checksum: &Checksum,
+) -> Result<()> {
+    let file_name = file
+        .as_ref()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    tokio::fs::write(manifest, format!("{checksum}  {file_name}\n")).await?;
+    Ok(())
+}
+
+/// Reads a manifest, returning the checksum and the path of the file it describes.
+pub async fn read_manifest(manifest: impl AsRef<Path>) -> Result<(Checksum, PathBuf)> {
+    let manifest = manifest.as_ref();
+    let text = tokio::fs::read_to_string(manifest).await?;
+    let (checksum, file_name) = text
+        .trim_end()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| Error::InvalidInput("malformed checksum manifest".into()))?;
+    // `sha256sum` marks files hashed in binary mode with a leading `*`.
+    let file_name = file_name.trim_start().trim_start_matches('*');
+    Ok((checksum.parse()?, manifest.with_file_name(file_name)))
+}
+
+/// Re-hashes a local backup file and compares it with its manifest. `path` is either
+/// the manifest itself or the backup file, whose manifest is `<path>.sha256`.
+pub async fn verify_backup_file(path: impl AsRef<Path>) -> Result<Checksum> {
+    let path = path.as_ref();
+    let (expected, file) = if path.extension().is_some_and(|ext| ext == "sha256") {
+        read_manifest(path).await?
+    } else {
+        let (expected, _) = read_manifest(manifest_path(path)).await?;
+        (expected, path.to_path_buf())
+    };
+    let actual = Checksum::of_reader(tokio::fs::File::open(file).await?).await?;
+    actual.verify(&expected)?;
+    Ok(actual)
+}
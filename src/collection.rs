@@ -0,0 +1,123 @@
+use crate::{BatchOperation, CollectionEvent, PutResult, QueryBuilder, Result, SmolKv, Versioned};
+use futures_util::stream::{BoxStream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Handle to a single collection whose values are all of type `T`.
+///
+/// Created with [`SmolKv::collection`]. Cloning is cheap.
+pub struct Collection<T> {
+    kv: SmolKv,
+    name: Arc<str>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Collection<T> {
+    fn clone(&self) -> Self {
+        Self {
+            kv: self.kv.clone(),
+            name: self.name.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Collection<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Collection")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<T> Collection<T> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kv(&self) -> &SmolKv {
+        &self.kv
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<bool> {
+        self.kv.delete(&self.name, key).await
+    }
+
+    pub async fn exists(&self, key: &str) -> Result<bool> {
+        self.kv.exists(&self.name, key).await
+    }
+}
+
+impl<T: DeserializeOwned> Collection<T> {
+    pub async fn get(&self, key: &str) -> Result<T> {
+        self.kv.get(&self.name, key).await
+    }
+
+    pub async fn get_with_version(&self, key: &str) -> Result<Versioned<T>> {
+        self.kv.get_with_version(&self.name, key).await
+    }
+
+    pub async fn query(&self, query: QueryBuilder) -> Result<Vec<T>> {
+        decode_all(self.kv.query_collection(&self.name, query).await?)
+    }
+
+    pub async fn list(&self, query: QueryBuilder) -> Result<Vec<T>> {
+        decode_all(self.kv.list_collection(&self.name, query).await?)
+    }
+}
+
+impl<T: Serialize> Collection<T> {
+    pub async fn put(&self, key: &str, value: &T) -> Result<PutResult> {
+        self.kv.put(&self.name, key, value).await
+    }
+
+    pub async fn put_if_absent(&self, key: &str, value: &T) -> Result<PutResult> {
+        self.kv.put_if_absent(&self.name, key, value).await
+    }
+
+    pub async fn put_if_match(&self, key: &str, value: &T, version: &str) -> Result<PutResult> {
+        self.kv.put_if_match(&self.name, key, value, version).await
+    }
+
+    pub async fn batch_put(&self, items: &[BatchOperation<T>]) -> Result<()> {
+        self.kv.batch_put(&self.name, items).await
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// See [`SmolKv::update`].
+    pub async fn update(&self, key: &str, f: impl FnMut(Option<T>) -> T) -> Result<T> {
+        self.kv.update(&self.name, key, f).await
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Collection<T> {
+    /// Change feed with values decoded as `T`. Values are `None` for deletions.
+    pub fn subscribe(&self) -> BoxStream<'static, Result<CollectionEvent<Option<T>>>> {
+        self.kv
+            .subscribe_events(&self.name)
+            .map(|event| event.and_then(CollectionEvent::decode))
+            .boxed()
+    }
+}
+
+fn decode_all<T: DeserializeOwned>(values: Vec<Value>) -> Result<Vec<T>> {
+    values
+        .into_iter()
+        .map(|v| Ok(serde_json::from_value(v)?))
+        .collect()
+}
+
+impl SmolKv {
+    /// Returns a typed handle to the `name` collection.
+    pub fn collection<T>(&self, name: &str) -> Collection<T> {
+        Collection {
+            kv: self.clone(),
+            name: Arc::from(name),
+            _marker: PhantomData,
+        }
+    }
+}
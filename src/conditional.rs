@@ -0,0 +1,135 @@
+use crate::{Error, PutResult, Result, SmolKv, Target};
+use reqwest::header::{ETAG, IF_MATCH, IF_NONE_MATCH};
+use reqwest::Method;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// How many times [`SmolKv::update`] re-reads and retries after a conflicting write.
+const MAX_UPDATE_ATTEMPTS: u32 = 10;
+
+/// A value together with the version the server reported for it.
+#[derive(Debug, Clone)]
+pub struct Versioned<T> {
+    pub value: T,
+    /// Opaque version from the `ETag` header, to be passed back to
+    /// [`SmolKv::put_if_match`]. `None` if the server does not report versions.
+    pub version: Option<String>,
+}
+
+impl SmolKv {
+    /// Fetches a value and its current version.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.get_with_version", skip_all)
+    )]
+    pub async fn get_with_version<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        key: &str,
+    ) -> Result<Versioned<T>> {
+        let req = self.request(Method::GET, self.key_url(collection, key)?);
+        let resp = self
+            .send(req, Target::key("get_with_version", collection, key))
+            .await?;
+        let version = resp
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let value = Self::handle_response(resp).await?;
+        Ok(Versioned { value, version })
+    }
+
+    /// Stores `value` only if `key` does not exist yet. Fails with [`Error::Conflict`]
+    /// otherwise.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.put_if_absent", skip_all)
+    )]
+    pub async fn put_if_absent<T: Serialize>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+    ) -> Result<PutResult> {
+        let req = self
+            .request(Method::PUT, self.key_url(collection, key)?)
+            .header(IF_NONE_MATCH, "*")
+            .json(value);
+        self.fetch(
+            req,
+            Target::key("put_if_absent", collection, key).non_idempotent(),
+        )
+        .await
+    }
+
+    /// Stores `value` only if the key is still at `version`, as returned by
+    /// [`SmolKv::get_with_version`]. Fails with [`Error::Conflict`] if it changed since.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.put_if_match", skip_all)
+    )]
+    pub async fn put_if_match<T: Serialize>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+        version: &str,
+    ) -> Result<PutResult> {
+        let req = self
+            .request(Method::PUT, self.key_url(collection, key)?)
+            .header(IF_MATCH, version)
+            .json(value);
+        self.fetch(
+            req,
+            Target::key("put_if_match", collection, key).non_idempotent(),
+        )
+        .await
+    }
+
+    /// Read-modify-write of a single key. `f` receives the current value, or `None` if
+    /// the key does not exist, and returns the value to store. When another writer gets
+    /// in between, the value is read again and `f` called again.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.update", skip_all)
+    )]
+    pub async fn update<T, F>(&self, collection: &str, key: &str, mut f: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnMut(Option<T>) -> T,
+    {
+        let mut attempt = 1;
+        loop {
+            let result = match self.get_with_version::<T>(collection, key).await {
+                Ok(Versioned {
+                    value,
+                    version: Some(version),
+                }) => {
+                    let new = f(Some(value));
+                    self.put_if_match(collection, key, &new, &version)
+                        .await
+                        .map(|_| new)
+                }
+                Ok(Versioned { version: None, .. }) => {
+                    return Err(Error::InvalidInput(format!(
+                        "server did not return a version for {collection}/{key}, \
+                         conditional updates are not available"
+                    )))
+                }
+                Err(Error::NotFound(_)) => {
+                    let new = f(None);
+                    self.put_if_absent(collection, key, &new).await.map(|_| new)
+                }
+                Err(e) => return Err(e),
+            };
+
+            match result {
+                Err(e @ Error::Conflict(_)) if attempt < MAX_UPDATE_ATTEMPTS => {
+                    tokio::time::sleep(self.retry.delay(attempt, &e)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
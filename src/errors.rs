@@ -1,4 +1,10 @@
+use crate::JobState;
+use reqwest::{header::RETRY_AFTER, Method, Response, StatusCode};
+use serde_json::Value;
+use std::fmt;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("http error: {0}")]
@@ -8,11 +14,195 @@ pub enum Error {
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
     #[error("not found: {0}")]
-    NotFound(String),
+    NotFound(Box<ErrorContext>),
     #[error("already exists: {0}")]
-    AlreadyExists(String),
+    AlreadyExists(Box<ErrorContext>),
+    /// A conditional write found the key in a different state than expected.
+    #[error("conflict: {0}")]
+    Conflict(Box<ErrorContext>),
     #[error("bad request: {0}")]
-    BadRequest(String),
+    BadRequest(Box<ErrorContext>),
+    #[error("unauthorized: {0}")]
+    Unauthorized(Box<ErrorContext>),
+    #[error("forbidden: {0}")]
+    Forbidden(Box<ErrorContext>),
+    #[error("rate limited: {context}")]
+    RateLimited {
+        retry_after: Option<Duration>,
+        context: Box<ErrorContext>,
+    },
+    #[error("payload too large: {0}")]
+    PayloadTooLarge(Box<ErrorContext>),
+    #[error("request timed out: {0}")]
+    Timeout(Box<ErrorContext>),
+    #[error("service unavailable: {context}")]
+    Unavailable {
+        retry_after: Option<Duration>,
+        context: Box<ErrorContext>,
+    },
     #[error("server error: {0}")]
-    Server(String),
+    Server(Box<ErrorContext>),
+    #[error("unexpected status: {0}")]
+    UnexpectedStatus(Box<ErrorContext>),
+    #[error("job {id} failed: {}", reason.as_deref().unwrap_or("no reason given"))]
+    JobFailed { id: String, reason: Option<String> },
+    #[error("timed out waiting for job {id} (last state: {state:?})")]
+    JobTimeout { id: String, state: JobState },
+    /// The server started a job without telling us its id.
+    #[error("server returned no job id for {collection}")]
+    MissingJobId { collection: String },
+    #[error("checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+}
+
+/// Details of the request that produced an [`Error`](enum@Error).
+#[derive(Debug, Clone)]
+pub struct ErrorContext {
+    pub method: Method,
+    pub path: String,
+    pub status: Option<StatusCode>,
+    pub collection: Option<String>,
+    pub key: Option<String>,
+    /// Response body, parsed as JSON when possible and kept as a string otherwise.
+    pub body: Option<Value>,
+}
+
+impl ErrorContext {
+    /// Best-effort human readable message from the response body.
+    pub fn message(&self) -> Option<String> {
+        match self.body.as_ref()? {
+            Value::String(s) => Some(s.clone()),
+            Value::Object(map) => ["error", "message", "detail"]
+                .iter()
+                .find_map(|field| map.get(*field))
+                .map(|v| match v {
+                    Value::String(s) => s.clone(),
+                    v => v.to_string(),
+                })
+                .or_else(|| Some(Value::Object(map.clone()).to_string())),
+            v => Some(v.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.path)?;
+        if let Some(status) = self.status {
+            write!(f, " ({status})")?;
+        }
+        if let Some(message) = self.message() {
+            write!(f, ": {message}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    pub(crate) async fn from_response(mut context: ErrorContext, resp: Response) -> Self {
+        let status = resp.status();
+        let retry_after = resp
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+
+        context.status = Some(status);
+        context.body = match resp.text().await {
+            Ok(text) if text.is_empty() => None,
+            Ok(text) => Some(serde_json::from_str(&text).unwrap_or(Value::String(text))),
+            Err(_) => None,
+        };
+        let context = Box::new(context);
+
+        match status {
+            StatusCode::BAD_REQUEST => Error::BadRequest(context),
+            StatusCode::UNAUTHORIZED => Error::Unauthorized(context),
+            StatusCode::FORBIDDEN => Error::Forbidden(context),
+            StatusCode::NOT_FOUND => Error::NotFound(context),
+            StatusCode::CONFLICT => Error::AlreadyExists(context),
+            StatusCode::PRECONDITION_FAILED => Error::Conflict(context),
+            StatusCode::PAYLOAD_TOO_LARGE => Error::PayloadTooLarge(context),
+            StatusCode::TOO_MANY_REQUESTS => Error::RateLimited {
+                retry_after,
+                context,
+            },
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => Error::Timeout(context),
+            StatusCode::SERVICE_UNAVAILABLE => Error::Unavailable {
+                retry_after,
+                context,
+            },
+            s if s.is_server_error() => Error::Server(context),
+            _ => Error::UnexpectedStatus(context),
+        }
+    }
+
+    pub(crate) fn from_transport(context: ErrorContext, err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Error::Timeout(Box::new(context))
+        } else {
+            Error::Http(err)
+        }
+    }
+
+    /// Request details for errors returned by the server.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::NotFound(c)
+            | Error::AlreadyExists(c)
+            | Error::Conflict(c)
+            | Error::BadRequest(c)
+            | Error::Unauthorized(c)
+            | Error::Forbidden(c)
+            | Error::PayloadTooLarge(c)
+            | Error::Timeout(c)
+            | Error::Server(c)
+            | Error::UnexpectedStatus(c)
+            | Error::RateLimited { context: c, .. }
+            | Error::Unavailable { context: c, .. } => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Http(e) => e.status(),
+            e => e.context().and_then(|c| c.status),
+        }
+    }
+
+    /// How long the server asked us to wait before trying again.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::RateLimited { retry_after, .. } | Error::Unavailable { retry_after, .. } => {
+                *retry_after
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed if sent again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Http(e) => e.is_connect() || e.is_timeout(),
+            Error::Timeout(_) | Error::RateLimited { .. } | Error::Unavailable { .. } => true,
+            Error::Server(c) => c.status != Some(StatusCode::NOT_IMPLEMENTED),
+            _ => false,
+        }
+    }
+}
+
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(secs) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value.trim()).ok()?;
+    Some(
+        at.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
 }
@@ -0,0 +1,363 @@
+use crate::{CollectionEvent, Error, Result, SmolKv};
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Change feed of a collection, as returned by [`SmolKv::subscribe_events`].
+pub type EventStream = BoxStream<'static, Result<CollectionEvent>>;
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// Lower bound for server `retry` hints, so `retry: 0` cannot cause a reconnect loop.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(50);
+/// Event types carrying a [`CollectionEvent`]. Unnamed events count as `message`.
+const CHANGE_EVENTS: &[&str] = &["message", "change"];
+
+/// A single dispatched Server-Sent Event.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SseEvent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(default)]
+    pub data: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<u64>,
+}
+
+/// Incremental `text/event-stream` parser. Chunks may split or join events arbitrarily.
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    buf: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+    retry: Option<u64>,
+    skip_lf: bool,
+}
+
+impl SseParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            if self.skip_lf {
+                self.skip_lf = false;
+                if byte == b'\n' {
+                    continue;
+                }
+            }
+            match byte {
+                b'\r' | b'\n' => {
+                    self.skip_lf = byte == b'\r';
+                    let line = std::mem::take(&mut self.buf);
+                    if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                        events.push(event);
+                    }
+                }
+                _ => self.buf.push(byte),
+            }
+        }
+        events
+    }
+
+    /// Drops any partially received event, e.g. after the connection was lost.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(retry) = value.parse() {
+                    self.retry = Some(retry);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let retry = self.retry.take();
+        let id = self.id.take();
+        if self.data.is_empty() {
+            // An event without data still updates the stream state.
+            return (id.is_some() || retry.is_some()).then(|| SseEvent {
+                id,
+                retry,
+                ..SseEvent::default()
+            });
+        }
+
+        Some(SseEvent {
+            event,
+            data: std::mem::take(&mut self.data).join("\n"),
+            id,
+            retry,
+        })
+    }
+}
+
+struct Subscription {
+    kv: SmolKv,
+    collection: String,
+    body: Option<BoxStream<'static, reqwest::Result<Bytes>>>,
+    parser: SseParser,
+    pending: VecDeque<SseEvent>,
+    last_event_id: Option<String>,
+    last_server_time: Option<u64>,
+    base_delay: Duration,
+    attempts: u32,
+    connected_once: bool,
+    done: bool,
+}
+
+impl Subscription {
+    fn reconnect_delay(&self) -> Duration {
+        let factor = 2u32.saturating_pow(self.attempts.saturating_sub(1));
+        self.base_delay
+            .saturating_mul(factor)
+            .min(MAX_RECONNECT_DELAY)
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        let resp = self
+            .kv
+            .open_subscription(
+                &self.collection,
+                self.last_event_id.clone(),
+                self.last_server_time,
+            )
+            .await?;
+        self.body = Some(resp.bytes_stream().boxed());
+        self.connected_once = true;
+        Ok(())
+    }
+
+    async fn next_event(&mut self) -> Option<Result<CollectionEvent>> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            if let Some(event) = self.pending.pop_front() {
+                if let Some(id) = event.id {
+                    self.last_event_id = Some(id);
+                }
+                if let Some(retry) = event.retry {
+                    self.base_delay = Duration::from_millis(retry).max(MIN_RECONNECT_DELAY);
+                }
+                if event.data.is_empty() {
+                    continue;
+                }
+                self.attempts = 0;
+                // Other event types, like keep-alive pings, are not changes.
+                if let Some(name) = &event.event {
+                    if !CHANGE_EVENTS.contains(&name.as_str()) {
+                        continue;
+                    }
+                }
+
+                let parsed = serde_json::from_str::<CollectionEvent>(&event.data);
+                if let Ok(ev) = &parsed {
+                    #[cfg(feature = "metrics")]
+                    self.kv.metrics.record_subscribe_event(&self.collection);
+                    if ev.server_time.is_some() {
+                        self.last_server_time = ev.server_time;
+                    }
+                }
+                return Some(parsed.map_err(Error::from));
+            }
+
+            match self.body.as_mut() {
+                Some(body) => match body.next().await {
+                    Some(Ok(chunk)) => self.pending.extend(self.parser.push(&chunk)),
+                    Some(Err(_)) | None => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(collection = %self.collection, "subscription stream closed");
+                        self.body = None;
+                        self.parser.reset();
+                    }
+                },
+                None => {
+                    if self.connected_once {
+                        self.attempts = self.attempts.saturating_add(1);
+                        let delay = self.reconnect_delay();
+                        #[cfg(feature = "tracing")]
+                        tracing::info!(
+                            collection = %self.collection,
+                            attempt = self.attempts,
+                            delay_ms = delay.as_millis() as u64,
+                            last_event_id = self.last_event_id.as_deref(),
+                            "reconnecting subscription"
+                        );
+                        #[cfg(feature = "metrics")]
+                        self.kv.metrics.record_reconnect(&self.collection);
+                        tokio::time::sleep(delay).await;
+                    }
+                    if let Err(e) = self.connect().await {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(collection = %self.collection, error = %e, "subscription connect failed");
+                        if !self.connected_once || !e.is_retryable() {
+                            self.done = true;
+                            return Some(Err(e));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl SmolKv {
+    /// Opens a change feed, resuming after the event `last_event_id` or, for servers
+    /// that send no event ids, after the server time `since`.
+    pub(crate) async fn open_subscription(
+        &self,
+        collection: &str,
+        last_event_id: Option<String>,
+        since: Option<u64>,
+    ) -> Result<reqwest::Response> {
+        // The stream stays open, so the client's total timeout would cut it off.
+        let url = self.url(&[collection, "_subscribe"])?;
+        let mut req = self.streaming_request(reqwest::Method::GET, url);
+        if let Some(id) = last_event_id {
+            req = req.header("Last-Event-ID", id);
+        }
+        if let Some(since) = since {
+            req = req.query(&[("since", since)]);
+        }
+        self.send(req, crate::Target::collection("subscribe", collection))
+            .await
+    }
+
+    /// Subscribes to a collection's change feed, reconnecting with backoff when the
+    /// connection drops and resuming from the last seen event.
+    pub fn subscribe_events(&self, collection: &str) -> EventStream {
+        let subscription = Subscription {
+            kv: self.clone(),
+            collection: collection.to_string(),
+            body: None,
+            parser: SseParser::default(),
+            pending: VecDeque::new(),
+            last_event_id: None,
+            last_server_time: None,
+            base_delay: INITIAL_RECONNECT_DELAY,
+            attempts: 0,
+            connected_once: false,
+            done: false,
+        };
+
+        stream::unfold(subscription, |mut sub| async move {
+            let item = sub.next_event().await?;
+            Some((item, sub))
+        })
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(data: &str) -> SseEvent {
+        SseEvent {
+            data: data.to_string(),
+            ..SseEvent::default()
+        }
+    }
+
+    #[test]
+    fn events_split_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"da").is_empty());
+        assert!(parser.push(b"ta: hel").is_empty());
+        assert!(parser.push(b"lo\n").is_empty());
+        assert_eq!(parser.push(b"\n"), [data("hello")]);
+    }
+
+    #[test]
+    fn several_events_in_one_chunk() {
+        let mut parser = SseParser::default();
+        let events = parser.push(b"data: a\n\ndata: b\n\ndata: c");
+        assert_eq!(events, [data("a"), data("b")]);
+        assert_eq!(parser.push(b"\n\n"), [data("c")]);
+    }
+
+    #[test]
+    fn multi_line_data() {
+        let mut parser = SseParser::default();
+        let events = parser.push(b"data: first\ndata:second\ndata\n\n");
+        assert_eq!(events, [data("first\nsecond\n")]);
+    }
+
+    #[test]
+    fn crlf_and_cr_line_endings() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.push(b"data: a\r\n\r\n"), [data("a")]);
+        assert_eq!(parser.push(b"data: b\r\r"), [data("b")]);
+        // A CRLF split between chunks is still one line break.
+        assert!(parser.push(b"data: c\r").is_empty());
+        assert!(parser.push(b"\n").is_empty());
+        assert_eq!(parser.push(b"\r\n"), [data("c")]);
+    }
+
+    #[test]
+    fn comments_and_unknown_fields_are_ignored() {
+        let mut parser = SseParser::default();
+        let events = parser.push(b": keep-alive\n\nfoo: bar\ndata: x\n\n");
+        assert_eq!(events, [data("x")]);
+    }
+
+    #[test]
+    fn id_retry_and_event_fields() {
+        let mut parser = SseParser::default();
+        let events = parser.push(b"event: change\nid: 7\nretry: 1500\ndata: x\n\n");
+        assert_eq!(
+            events,
+            [SseEvent {
+                event: Some("change".into()),
+                data: "x".into(),
+                id: Some("7".into()),
+                retry: Some(1500),
+            }]
+        );
+
+        // Without data, only the stream state is updated; a bad retry is ignored.
+        let events = parser.push(b"id: 8\nretry: soon\n\n");
+        assert_eq!(
+            events,
+            [SseEvent {
+                id: Some("8".into()),
+                ..SseEvent::default()
+            }]
+        );
+        assert!(parser.push(b"event: ping\n\n").is_empty());
+    }
+
+    #[test]
+    fn reset_drops_partial_events() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: lost\nda").is_empty());
+        parser.reset();
+        assert_eq!(parser.push(b"data: kept\n\n"), [data("kept")]);
+    }
+}
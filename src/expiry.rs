@@ -0,0 +1,244 @@
+use crate::{BatchReport, Error, PutResult, QueryBuilder, Result, SmolKv, Target};
+use futures_util::future;
+use futures_util::stream::StreamExt;
+use reqwest::Method;
+use serde::{de::DeserializeOwned, de::IgnoredAny, Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Header carrying the remaining lifetime of a key, in seconds.
+const TTL_HEADER: &str = "X-TTL";
+const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+/// Lower bound for the sweep interval; `tokio::time::interval` panics on zero.
+const MIN_SWEEP_INTERVAL: Duration = Duration::from_millis(10);
+const DEFAULT_SWEEP_PAGE_SIZE: usize = 500;
+
+/// Whole seconds, rounded up so a key never expires early.
+fn ttl_secs(ttl: Duration) -> Result<u64> {
+    let secs = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+    if secs == 0 {
+        return Err(Error::InvalidInput("ttl must not be zero".into()));
+    }
+    Ok(secs)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl SmolKv {
+    /// Stores `value` and lets the server delete it after `ttl`.
+    ///
+    /// Servers that cannot expire keys may ignore the TTL; use an [`ExpirySweeper`]
+    /// for those.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.put_with_ttl", skip_all)
+    )]
+    pub async fn put_with_ttl<T: Serialize>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<PutResult> {
+        let req = self
+            .request(Method::PUT, self.key_url(collection, key)?)
+            .query(&[("ttl", ttl_secs(ttl)?)])
+            .json(value);
+        self.fetch(req, Target::key("put_with_ttl", collection, key))
+            .await
+    }
+
+    /// Remaining lifetime of a key, or `None` if it does not expire.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.ttl", skip_all)
+    )]
+    pub async fn ttl(&self, collection: &str, key: &str) -> Result<Option<Duration>> {
+        let req = self.request(Method::HEAD, self.key_url(collection, key)?);
+        let resp = self.send(req, Target::key("ttl", collection, key)).await?;
+        Ok(resp
+            .headers()
+            .get(TTL_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+            .map(Duration::from_secs))
+    }
+
+    /// Returns an [`ExpirySweeper`] that emulates key expiry for `collection` on the
+    /// client side.
+    pub fn expiry_sweeper(&self, collection: &str) -> ExpirySweeper {
+        ExpirySweeper {
+            kv: self.clone(),
+            collection: Arc::from(collection),
+            interval: DEFAULT_SWEEP_INTERVAL,
+            page_size: DEFAULT_SWEEP_PAGE_SIZE,
+        }
+    }
+}
+
+/// How values written by an [`ExpirySweeper`] are stored.
+#[derive(Debug, Deserialize, Serialize)]
+struct Envelope<T> {
+    value: T,
+    /// Unix time in seconds.
+    expires_at: u64,
+}
+
+/// Expiry time of a value stored as an [`Envelope`]. Other documents, even ones with an
+/// `expires_at` field of their own, have none.
+fn envelope_expiry(value: &Value) -> Option<u64> {
+    let fields = value.as_object()?;
+    if fields.len() != 2 || !fields.contains_key("value") {
+        return None;
+    }
+    fields.get("expires_at")?.as_u64()
+}
+
+/// Client-side expiry for servers that cannot expire keys themselves.
+///
+/// Values are stored as `{"value": ..., "expires_at": <unix seconds>}`, so they must be
+/// read back through the sweeper. Expired values are hidden from [`ExpirySweeper::get`]
+/// right away and deleted by [`ExpirySweeper::sweep`], which [`ExpirySweeper::spawn`]
+/// runs on a schedule.
+#[derive(Clone)]
+pub struct ExpirySweeper {
+    kv: SmolKv,
+    collection: Arc<str>,
+    interval: Duration,
+    page_size: usize,
+}
+
+impl fmt::Debug for ExpirySweeper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpirySweeper")
+            .field("collection", &self.collection)
+            .field("interval", &self.interval)
+            .field("page_size", &self.page_size)
+            .finish()
+    }
+}
+
+impl ExpirySweeper {
+    /// Time between two sweeps started by [`ExpirySweeper::spawn`], at least 10ms.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(MIN_SWEEP_INTERVAL);
+        self
+    }
+
+    /// Number of keys looked up, and of expired keys deleted, per request.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub async fn put_with_ttl<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<PutResult> {
+        let envelope = Envelope {
+            value,
+            expires_at: unix_now() + ttl_secs(ttl)?,
+        };
+        self.kv.put(&self.collection, key, &envelope).await
+    }
+
+    /// The stored value, or `None` if the key is missing or has expired.
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        match self.kv.get::<Envelope<T>>(&self.collection, key).await {
+            Ok(envelope) if envelope.expires_at > unix_now() => Ok(Some(envelope.value)),
+            Ok(_) | Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Remaining lifetime of a key, or `None` if it is missing or has expired.
+    pub async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        match self
+            .kv
+            .get::<Envelope<IgnoredAny>>(&self.collection, key)
+            .await
+        {
+            Ok(envelope) => Ok(envelope
+                .expires_at
+                .checked_sub(unix_now())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Deletes every key that has expired by now.
+    ///
+    /// Expiry is checked on the client, so the sweep pages through the whole collection
+    /// and works with servers that cannot filter queries. Only values shaped like
+    /// `{"value": ..., "expires_at": ...}` are considered; other documents are left
+    /// alone even if they have an `expires_at` field. A key rewritten between the lookup and the
+    /// delete can be removed even though its new value has not expired yet.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.sweep", skip_all)
+    )]
+    pub async fn sweep(&self) -> Result<BatchReport> {
+        let now = unix_now();
+        let mut expired = self
+            .kv
+            .query_stream::<Value>(&self.collection, QueryBuilder::new(), self.page_size)
+            .filter_map(|item| {
+                future::ready(match item {
+                    Ok((key, value)) => envelope_expiry(&value)
+                        .filter(|expires_at| *expires_at <= now)
+                        .map(|_| Ok(key)),
+                    Err(e) => Some(Err(e)),
+                })
+            })
+            .chunks(self.page_size);
+
+        let mut report = BatchReport::default();
+        while let Some(keys) = expired.next().await {
+            let keys = keys.into_iter().collect::<Result<Vec<_>>>()?;
+            report.merge(self.kv.batch_delete(&self.collection, &keys).await?);
+        }
+        Ok(report)
+    }
+
+    /// Runs [`ExpirySweeper::sweep`] every interval until the returned handle is dropped.
+    /// A failed sweep is retried at the next interval and, with the `tracing` feature,
+    /// logged as a warning.
+    pub fn spawn(self) -> SweeperHandle {
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(self.interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                if let Err(e) = self.sweep().await {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(collection = %self.collection, error = %e, "expiry sweep failed");
+                }
+            }
+        });
+        SweeperHandle { task }
+    }
+}
+
+/// Background task started by [`ExpirySweeper::spawn`]. Dropping it stops the task.
+#[derive(Debug)]
+pub struct SweeperHandle {
+    task: JoinHandle<()>,
+}
+
+impl Drop for SweeperHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
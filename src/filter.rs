@@ -0,0 +1,279 @@
+use crate::{Error, QueryBuilder, Result};
+
+/// A JSONPath filter expression, rendered for [`QueryBuilder::filter`].
+///
+/// ```
+/// use smolkv_client::Filter;
+///
+/// let filter = Filter::field("owner.login")
+///     .eq("bob")
+///     .and(Filter::field("stars").gt(10));
+/// assert_eq!(
+///     filter.to_jsonpath().unwrap(),
+///     r#"$[?(@.owner.login == "bob" && @.stars > 10)]"#
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter(Expr);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Compare(Field, CompareOp, Literal),
+    Exists(Field),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl CompareOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+        }
+    }
+}
+
+/// A literal value a field can be compared with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    String(String),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+
+impl From<&str> for Literal {
+    fn from(v: &str) -> Self {
+        Literal::String(v.to_string())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(v: String) -> Self {
+        Literal::String(v)
+    }
+}
+
+impl From<i32> for Literal {
+    fn from(v: i32) -> Self {
+        Literal::Int(v.into())
+    }
+}
+
+impl From<i64> for Literal {
+    fn from(v: i64) -> Self {
+        Literal::Int(v)
+    }
+}
+
+impl From<u32> for Literal {
+    fn from(v: u32) -> Self {
+        Literal::UInt(v.into())
+    }
+}
+
+impl From<u64> for Literal {
+    fn from(v: u64) -> Self {
+        Literal::UInt(v)
+    }
+}
+
+impl From<f64> for Literal {
+    fn from(v: f64) -> Self {
+        Literal::Float(v)
+    }
+}
+
+impl From<bool> for Literal {
+    fn from(v: bool) -> Self {
+        Literal::Bool(v)
+    }
+}
+
+impl<T: Into<Literal>> From<Option<T>> for Literal {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Literal::Null, Into::into)
+    }
+}
+
+impl Literal {
+    fn render(&self) -> Result<String> {
+        Ok(match self {
+            Literal::String(s) => serde_json::to_string(s)?,
+            Literal::Int(i) => i.to_string(),
+            Literal::UInt(u) => u.to_string(),
+            Literal::Float(f) if f.is_finite() => {
+                serde_json::Number::from_f64(*f).map_or_else(|| f.to_string(), |n| n.to_string())
+            }
+            Literal::Float(f) => {
+                return Err(Error::InvalidInput(format!(
+                    "{f} cannot be used in a filter"
+                )))
+            }
+            Literal::Bool(b) => b.to_string(),
+            Literal::Null => "null".to_string(),
+        })
+    }
+}
+
+/// A path into the stored JSON documents, e.g. `owner.login`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    segments: Vec<String>,
+}
+
+impl Field {
+    fn compare(self, op: CompareOp, value: impl Into<Literal>) -> Filter {
+        Filter(Expr::Compare(self, op, value.into()))
+    }
+
+    pub fn eq(self, value: impl Into<Literal>) -> Filter {
+        self.compare(CompareOp::Eq, value)
+    }
+
+    pub fn ne(self, value: impl Into<Literal>) -> Filter {
+        self.compare(CompareOp::Ne, value)
+    }
+
+    pub fn gt(self, value: impl Into<Literal>) -> Filter {
+        self.compare(CompareOp::Gt, value)
+    }
+
+    pub fn ge(self, value: impl Into<Literal>) -> Filter {
+        self.compare(CompareOp::Ge, value)
+    }
+
+    pub fn lt(self, value: impl Into<Literal>) -> Filter {
+        self.compare(CompareOp::Lt, value)
+    }
+
+    pub fn le(self, value: impl Into<Literal>) -> Filter {
+        self.compare(CompareOp::Le, value)
+    }
+
+    /// Matches documents where the field is present.
+    pub fn exists(self) -> Filter {
+        Filter(Expr::Exists(self))
+    }
+
+    fn render(&self) -> Result<String> {
+        if self.segments.is_empty() {
+            return Err(Error::InvalidInput("filter field path is empty".into()));
+        }
+
+        let mut out = String::from("@");
+        for segment in &self.segments {
+            if segment.is_empty() {
+                return Err(Error::InvalidInput(format!(
+                    "filter field path '{}' has an empty segment",
+                    self.segments.join(".")
+                )));
+            }
+            if is_identifier(segment) {
+                out.push('.');
+                out.push_str(segment);
+            } else {
+                out.push('[');
+                out.push_str(&serde_json::to_string(segment)?);
+                out.push(']');
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn is_identifier(segment: &str) -> bool {
+    let mut chars = segment.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Filter {
+    /// Field addressed with dot notation. Use [`Filter::field_path`] for names containing dots.
+    pub fn field(path: &str) -> Field {
+        Field {
+            segments: path.split('.').map(str::to_string).collect(),
+        }
+    }
+
+    /// Field addressed by its individual path segments, taken literally.
+    pub fn field_path<S: Into<String>>(segments: impl IntoIterator<Item = S>) -> Field {
+        Field {
+            segments: segments.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn and(self, other: Filter) -> Filter {
+        Filter(Expr::And(Box::new(self.0), Box::new(other.0)))
+    }
+
+    pub fn or(self, other: Filter) -> Filter {
+        Filter(Expr::Or(Box::new(self.0), Box::new(other.0)))
+    }
+
+    /// Validates the expression and renders it as a JSONPath filter selector.
+    pub fn to_jsonpath(&self) -> Result<String> {
+        Ok(format!("$[?({})]", self.0.render()?))
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter(Expr::Not(Box::new(self.0)))
+    }
+}
+
+impl Expr {
+    fn render(&self) -> Result<String> {
+        Ok(match self {
+            Expr::Compare(field, op, value) => {
+                format!("{} {} {}", field.render()?, op.as_str(), value.render()?)
+            }
+            Expr::Exists(field) => field.render()?,
+            Expr::And(l, r) => format!("{} && {}", l.render_within(self)?, r.render_within(self)?),
+            Expr::Or(l, r) => format!("{} || {}", l.render_within(self)?, r.render_within(self)?),
+            Expr::Not(inner) => format!("!({})", inner.render()?),
+        })
+    }
+
+    /// Renders `self` as an operand of `parent`, adding parentheses when the
+    /// operators differ so precedence never depends on the server's parser.
+    fn render_within(&self, parent: &Expr) -> Result<String> {
+        let same_op = matches!(
+            (self, parent),
+            (Expr::And(..), Expr::And(..)) | (Expr::Or(..), Expr::Or(..))
+        );
+        match self {
+            Expr::And(..) | Expr::Or(..) if !same_op => Ok(format!("({})", self.render()?)),
+            _ => self.render(),
+        }
+    }
+}
+
+impl QueryBuilder {
+    /// Sets the query from a typed [`Filter`], validating it first.
+    pub fn filter(self, filter: &Filter) -> Result<Self> {
+        Ok(self.query(filter.to_jsonpath()?))
+    }
+}
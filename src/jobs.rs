@@ -0,0 +1,197 @@
+use crate::{BackupJob, Error, JobState, RestoreJob, Result, SmolKv};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Snapshot reported to [`WaitOptions::on_progress`] after every poll.
+#[derive(Debug, Clone)]
+pub struct JobProgress {
+    pub id: String,
+    pub state: JobState,
+    pub polls: u32,
+    pub elapsed: Duration,
+}
+
+type ProgressFn = Arc<dyn Fn(&JobProgress) + Send + Sync>;
+
+/// Polling behaviour of [`SmolKv::wait_for_backup`] and [`SmolKv::wait_for_restore`].
+#[derive(Clone)]
+pub struct WaitOptions {
+    interval: Duration,
+    timeout: Option<Duration>,
+    progress: Option<ProgressFn>,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            timeout: Some(Duration::from_secs(60 * 60)),
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for WaitOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitOptions")
+            .field("interval", &self.interval)
+            .field("timeout", &self.timeout)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl WaitOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Overall deadline. `None` waits until the job finishes.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn on_progress(mut self, progress: impl Fn(&JobProgress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+}
+
+pub(crate) trait Job {
+    fn state(&self) -> JobState;
+    fn error(&self) -> Option<&str>;
+}
+
+impl Job for BackupJob {
+    fn state(&self) -> JobState {
+        self.status
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl Job for RestoreJob {
+    fn state(&self) -> JobState {
+        self.status
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+async fn wait_for<J, F, Fut>(id: &str, opts: &WaitOptions, mut poll: F) -> Result<J>
+where
+    J: Job,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<J>>,
+{
+    let started = Instant::now();
+    let mut polls = 0;
+    #[cfg(feature = "tracing")]
+    let mut last_state = None;
+
+    loop {
+        let job = poll().await?;
+        polls += 1;
+
+        #[cfg(feature = "tracing")]
+        if last_state != Some(job.state()) {
+            tracing::info!(job = id, state = ?job.state(), polls, "job state changed");
+            last_state = Some(job.state());
+        }
+
+        let progress = JobProgress {
+            id: id.to_string(),
+            state: job.state(),
+            polls,
+            elapsed: started.elapsed(),
+        };
+        if let Some(report) = &opts.progress {
+            report(&progress);
+        }
+
+        match job.state() {
+            JobState::Completed => return Ok(job),
+            JobState::Failed => {
+                return Err(Error::JobFailed {
+                    id: id.to_string(),
+                    reason: job.error().map(str::to_string),
+                })
+            }
+            state => {
+                let mut delay = opts.interval;
+                if let Some(timeout) = opts.timeout {
+                    let remaining = timeout.saturating_sub(started.elapsed());
+                    if remaining.is_zero() {
+                        return Err(Error::JobTimeout {
+                            id: id.to_string(),
+                            state,
+                        });
+                    }
+                    // Poll once more at the deadline instead of giving up early.
+                    delay = delay.min(remaining);
+                }
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+impl SmolKv {
+    /// Polls a backup until it completes, fails or the deadline passes.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.wait_for_backup", skip_all)
+    )]
+    pub async fn wait_for_backup(
+        &self,
+        collection: &str,
+        id: &str,
+        opts: WaitOptions,
+    ) -> Result<BackupJob> {
+        wait_for(id, &opts, || self.backup_status(collection, id)).await
+    }
+
+    /// Polls a restore until it completes, fails or the deadline passes.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.wait_for_restore", skip_all)
+    )]
+    pub async fn wait_for_restore(
+        &self,
+        collection: &str,
+        id: &str,
+        opts: WaitOptions,
+    ) -> Result<RestoreJob> {
+        wait_for(id, &opts, || self.restore_status(collection, id)).await
+    }
+
+    /// Starts a backup, waits for it with default [`WaitOptions`] and downloads it.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.backup_and_download", skip_all)
+    )]
+    pub async fn backup_and_download(&self, collection: &str) -> Result<bytes::Bytes> {
+        let id = self
+            .start_backup(collection)
+            .await?
+            .id
+            .ok_or_else(|| Error::MissingJobId {
+                collection: collection.to_string(),
+            })?;
+        self.wait_for_backup(collection, &id, WaitOptions::default())
+            .await?;
+        self.download_backup(collection, &id).await
+    }
+}
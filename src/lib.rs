@@ -1,8 +1,87 @@
-use reqwest::{Client, StatusCode};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use reqwest::{
+    header::{HeaderMap, IF_MATCH, IF_NONE_MATCH},
+    Client, IntoUrl, Method, RequestBuilder, Url,
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+mod batch;
+mod batch_writer;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+mod cache;
+mod capabilities;
+pub mod checksum;
+mod collection;
+mod conditional;
 mod errors;
-pub use errors::Error;
+mod events;
+mod expiry;
+mod filter;
+mod jobs;
+mod memory;
+#[cfg(feature = "metrics")]
+mod metrics_sink;
+mod models;
+mod paginate;
+mod retry;
+mod store;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tracing")]
+mod trace;
+mod transfer;
+mod transport;
+pub use batch::{BatchFailure, BatchGetReport, BatchMode, BatchOutcome, BatchReport, Op};
+pub use batch_writer::BatchWriter;
+pub use builder::SmolKvBuilder;
+pub use cache::{CacheStats, CachedKv};
+use capabilities::Capabilities;
+pub use checksum::Checksum;
+pub use collection::Collection;
+pub use conditional::Versioned;
+pub use errors::{Error, ErrorContext};
+pub use events::EventStream;
+pub use expiry::{ExpirySweeper, SweeperHandle};
+pub use filter::{Field, Filter, Literal};
+pub use jobs::{JobProgress, WaitOptions};
+pub use memory::MemoryKv;
+#[cfg(feature = "metrics")]
+pub use metrics_sink::{MetricsFacade, MetricsSink, RequestStats};
+pub use models::{
+    BackupJob, CollectionInfo, ImportFailure, ImportReport, JobState, PutResult, RestoreJob,
+};
+pub use retry::RetryPolicy;
+pub use store::KvStore;
+pub use transfer::{ByteStream, DownloadReport, TransferProgress};
+pub use transport::{
+    ConcurrencyLimitLayer, Layer, RateLimitLayer, SharedTransport, TimeoutLayer, Transport,
+};
+
+/// Characters escaped in collection names and keys: controls, and everything with a
+/// meaning in URLs except the unreserved and sub-delimiter characters.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Path segments below a collection that the server routes itself instead of
+/// treating them as keys.
+const RESERVED_KEYS: &[&str] = &["_batch", "_subscribe", "_import", "_backup", "_restore"];
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -20,15 +99,28 @@ pub struct BatchOperation<T> {
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct CollectionEvent {
+pub struct CollectionEvent<T = Value> {
     pub operation: String,
     pub key: String,
-    pub value: Value,
+    #[serde(default)]
+    pub value: T,
     #[serde(default)]
     pub server_time: Option<u64>,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+impl CollectionEvent {
+    /// Converts the event's JSON value into `T`.
+    pub fn decode<T: DeserializeOwned>(self) -> Result<CollectionEvent<T>> {
+        Ok(CollectionEvent {
+            operation: self.operation,
+            key: self.key,
+            value: serde_json::from_value(self.value)?,
+            server_time: self.server_time,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct QueryBuilder {
     from: Option<String>,
     to: Option<String>,
@@ -77,232 +169,523 @@ impl QueryBuilder {
 
 #[derive(Clone)]
 pub struct SmolKv {
-    endpoint: String,
+    endpoint: Url,
+    /// Only used to build requests; they are sent through `transport`.
     client: Client,
+    transport: SharedTransport,
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    batch_concurrency: usize,
+    capabilities: Arc<Capabilities>,
+    #[cfg(feature = "tracing")]
+    redact_keys: bool,
+    #[cfg(feature = "tracing")]
+    trace_context: Option<trace::TraceContext>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<dyn MetricsSink>,
+}
+
+/// Operation, collection and key a request is about, and whether it is safe to repeat.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Target<'a> {
+    /// Name of the public method sending the request, e.g. `put_if_match`.
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub operation: &'static str,
+    pub collection: Option<&'a str>,
+    pub key: Option<&'a str>,
+    pub idempotent: bool,
+}
+
+impl<'a> Target<'a> {
+    pub fn collection(operation: &'static str, collection: &'a str) -> Self {
+        Self {
+            operation,
+            collection: Some(collection),
+            key: None,
+            idempotent: true,
+        }
+    }
+
+    pub fn key(operation: &'static str, collection: &'a str, key: &'a str) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::collection(operation, collection)
+        }
+    }
+
+    pub fn non_idempotent(self) -> Self {
+        Self {
+            idempotent: false,
+            ..self
+        }
+    }
 }
 
 impl SmolKv {
-    pub fn new(endpoint: impl Into<String>, secret: Option<impl Into<String>>) -> Self {
-        let client = match secret {
-            Some(secret_key) => {
-                let mut headers = reqwest::header::HeaderMap::new();
-                headers.insert("X-SECRET-KEY", secret_key.into().parse().unwrap());
-                Client::builder().default_headers(headers).build().unwrap()
+    pub fn new(endpoint: impl Into<String>, secret: Option<impl Into<String>>) -> Result<Self> {
+        Self::builder(endpoint).secret(secret).build()
+    }
+
+    pub fn builder(endpoint: impl Into<String>) -> SmolKvBuilder {
+        SmolKvBuilder::new(endpoint)
+    }
+
+    /// Builds a URL below the endpoint, percent-encoding every segment.
+    fn endpoint_url(&self, segments: &[&str]) -> Result<Url> {
+        if let Some(segment) = segments
+            .iter()
+            .find(|s| s.is_empty() || **s == "." || **s == "..")
+        {
+            return Err(Error::InvalidInput(format!(
+                "'{segment}' cannot be used as a collection name or key"
+            )));
+        }
+
+        // Encoded here rather than with `path_segments_mut`, which silently drops tabs
+        // and newlines.
+        let mut path = self.endpoint.path().trim_end_matches('/').to_string();
+        for segment in segments {
+            path.push('/');
+            path.extend(utf8_percent_encode(segment, PATH_SEGMENT));
+        }
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+        Ok(url)
+    }
+
+    fn url(&self, segments: &[&str]) -> Result<Url> {
+        let mut all = Vec::with_capacity(segments.len() + 1);
+        all.push("api");
+        all.extend_from_slice(segments);
+        self.endpoint_url(&all)
+    }
+
+    /// URL of a key. Keys named like the collection routes are rejected, since the
+    /// server would treat them as those routes.
+    fn key_url(&self, collection: &str, key: &str) -> Result<Url> {
+        if RESERVED_KEYS.contains(&key) {
+            return Err(Error::InvalidInput(format!(
+                "'{key}' is reserved and cannot be used as a key"
+            )));
+        }
+        self.url(&[collection, key])
+    }
+
+    fn backup_file_url(&self, collection: &str, backup_id: &str) -> Result<Url> {
+        self.endpoint_url(&["backups", &format!("{collection}-{backup_id}.sst")])
+    }
+
+    fn request(&self, method: Method, url: impl IntoUrl) -> RequestBuilder {
+        let builder = self.streaming_request(method, url);
+        match self.timeout {
+            Some(timeout) => builder.timeout(timeout),
+            None => builder,
+        }
+    }
+
+    /// A request without the total timeout, for responses that stay open indefinitely.
+    fn streaming_request(&self, method: Method, url: impl IntoUrl) -> RequestBuilder {
+        self.client
+            .request(method, url)
+            .headers(self.headers.clone())
+    }
+
+    /// Sends a request according to the retry policy.
+    pub(crate) async fn send(
+        &self,
+        req: RequestBuilder,
+        target: Target<'_>,
+    ) -> Result<reqwest::Response> {
+        // Requests with streaming bodies are sent once and never retried.
+        let replayable = req.try_clone().is_some();
+        let req = Mutex::new(Some(req));
+        self.send_with(target, || {
+            let mut req = req.lock().unwrap_or_else(|e| e.into_inner());
+            match replayable {
+                true => req.as_ref()?.try_clone(),
+                false => req.take(),
+            }
+        })
+        .await
+    }
+
+    /// Like [`SmolKv::send`], rebuilding the request for every attempt. Used for
+    /// streaming bodies that cannot be cloned.
+    pub(crate) async fn send_with(
+        &self,
+        target: Target<'_>,
+        build: impl Fn() -> Option<RequestBuilder>,
+    ) -> Result<reqwest::Response> {
+        #[cfg(feature = "tracing")]
+        let trace = trace::RequestTrace::new(target, self.redact_keys, self.trace_context.as_ref());
+        #[cfg(feature = "metrics")]
+        let mut stats = metrics_sink::RequestTimer::start();
+        let attempts = async {
+            let mut attempt = 1;
+            let mut next = build();
+            loop {
+                let Some(req) = next.take() else {
+                    return Err(Error::InvalidInput(
+                        "request body cannot be replayed".into(),
+                    ));
+                };
+                let request = req.build()?;
+                #[cfg(feature = "tracing")]
+                let request =
+                    trace.start_attempt(request, !self.headers.contains_key(trace::TRACEPARENT));
+                #[cfg(feature = "metrics")]
+                stats.start_attempt(&request);
+                match self.execute(request, target).await {
+                    Ok(resp) => return Ok(resp),
+                    Err(e)
+                        if self.retry.allows(attempt, target.idempotent)
+                            && self.retry.should_retry(&e) =>
+                    {
+                        // A body that cannot be sent again fails right away, without
+                        // waiting out the backoff first.
+                        let Some(req) = build() else {
+                            return Err(e);
+                        };
+                        #[cfg(feature = "tracing")]
+                        trace.retrying(attempt, &e);
+                        tokio::time::sleep(self.retry.delay(attempt, &e)).await;
+                        attempt += 1;
+                        next = Some(req);
+                    }
+                    Err(e) => return Err(e),
+                }
             }
-            None => Client::new(),
         };
+        #[cfg(feature = "tracing")]
+        let result = trace.run(attempts).await;
+        #[cfg(not(feature = "tracing"))]
+        let result = attempts.await;
+        #[cfg(feature = "metrics")]
+        stats.finish(self.metrics.as_ref(), target, &result);
+        result
+    }
 
-        Self {
-            endpoint: endpoint.into(),
-            client,
+    /// Sends a request once, turning non-success statuses into typed errors.
+    async fn execute(
+        &self,
+        request: reqwest::Request,
+        target: Target<'_>,
+    ) -> Result<reqwest::Response> {
+        let conditional = [IF_MATCH, IF_NONE_MATCH]
+            .iter()
+            .any(|h| request.headers().contains_key(h));
+        let context = ErrorContext {
+            method: request.method().clone(),
+            path: request.url().path().to_string(),
+            status: None,
+            collection: target.collection.map(str::to_string),
+            key: target.key.map(str::to_string),
+            body: None,
+        };
+
+        // `reqwest::Client` enforces the timeout itself, but other transports may not.
+        let timeout = request.timeout().copied();
+        let sent = self.transport.send(request);
+        let sent = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, sent)
+                .await
+                .unwrap_or_else(|_| Err(Error::Timeout(Box::new(context.clone())))),
+            None => sent.await,
+        };
+        let resp = sent.map_err(|e| match e {
+            Error::Http(e) => Error::from_transport(context.clone(), e),
+            Error::Timeout(_) => Error::Timeout(Box::new(context.clone())),
+            e => e,
+        })?;
+
+        if resp.status().is_success() {
+            return Ok(resp);
+        }
+        match Error::from_response(context, resp).await {
+            // For conditional requests a 409 means the precondition did not hold.
+            Error::AlreadyExists(context) if conditional => Err(Error::Conflict(context)),
+            e => Err(e),
+        }
+    }
+
+    async fn handle_response<T: DeserializeOwned>(resp: reqwest::Response) -> Result<T> {
+        let body = resp.bytes().await?;
+        if body.is_empty() {
+            // No content: accept it as `null` or, for response structs, as an empty object.
+            return serde_json::from_value(Value::Null)
+                .or_else(|_| serde_json::from_value(Value::Object(Default::default())))
+                .map_err(Error::from);
         }
+        Ok(serde_json::from_slice(&body)?)
     }
 
-    fn url(&self, path: impl AsRef<str>) -> String {
-        let path = path.as_ref().trim_start_matches('/');
-        format!("{}/api/{}", self.endpoint, path)
+    async fn fetch<T: DeserializeOwned>(
+        &self,
+        req: RequestBuilder,
+        target: Target<'_>,
+    ) -> Result<T> {
+        let resp = self.send(req, target).await?;
+        Self::handle_response(resp).await
     }
 
-    async fn handle_response<T: DeserializeOwned>(resp: reqwest::Response) -> Result<T> {
-        match resp.status() {
-            StatusCode::OK | StatusCode::CREATED => Ok(resp.json().await?),
-            StatusCode::NOT_FOUND => Err(Error::NotFound(resp.url().path().to_string())),
-            StatusCode::CONFLICT => Err(Error::AlreadyExists(resp.url().path().to_string())),
-            StatusCode::BAD_REQUEST => Err(Error::BadRequest(resp.text().await?)),
-            s => Err(Error::Server(format!("unexpected status: {}", s))),
+    async fn check(&self, req: RequestBuilder, target: Target<'_>) -> Result<bool> {
+        match self.send(req, target).await {
+            Ok(_) => Ok(true),
+            Err(Error::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
         }
     }
 
     // collection operations
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.collection_exists", skip_all)
+    )]
     pub async fn collection_exists(&self, name: &str) -> Result<bool> {
-        Ok(self
-            .client
-            .head(self.url(name))
-            .send()
-            .await?
-            .status()
-            .is_success())
+        let req = self.request(Method::HEAD, self.url(&[name])?);
+        self.check(req, Target::collection("collection_exists", name))
+            .await
     }
 
-    pub async fn create_collection(&self, name: &str) -> Result<Value> {
-        let resp = self.client.put(self.url(name)).send().await?;
-
-        Self::handle_response(resp).await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.create_collection", skip_all)
+    )]
+    pub async fn create_collection(&self, name: &str) -> Result<CollectionInfo> {
+        let req = self.request(Method::PUT, self.url(&[name])?);
+        self.fetch(req, Target::collection("create_collection", name))
+            .await
     }
 
-    pub async fn drop_collection(&self, name: &str) -> Result<Value> {
-        let resp = self
-            .client
-            .delete(self.url(format!("/{}", name)))
-            .send()
-            .await?;
-
-        Self::handle_response(resp).await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.drop_collection", skip_all)
+    )]
+    pub async fn drop_collection(&self, name: &str) -> Result<CollectionInfo> {
+        let req = self.request(Method::DELETE, self.url(&[name])?);
+        self.fetch(req, Target::collection("drop_collection", name))
+            .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.list_collection", skip_all)
+    )]
     pub async fn list_collection(&self, name: &str, query: QueryBuilder) -> Result<Vec<Value>> {
-        let resp = self.client.get(self.url(name)).query(&query).send().await?;
-        Self::handle_response(resp).await
+        let req = self.request(Method::GET, self.url(&[name])?).query(&query);
+        self.fetch(req, Target::collection("list_collection", name))
+            .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.query_collection", skip_all)
+    )]
     pub async fn query_collection(&self, name: &str, query: QueryBuilder) -> Result<Vec<Value>> {
-        let resp = self.client.post(self.url(name)).json(&query).send().await?;
-        Self::handle_response(resp).await
+        let req = self.request(Method::POST, self.url(&[name])?).json(&query);
+        self.fetch(req, Target::collection("query_collection", name))
+            .await
     }
     // key operations
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.get", skip_all)
+    )]
     pub async fn get<T: DeserializeOwned>(&self, collection: &str, key: &str) -> Result<T> {
-        let resp = self
-            .client
-            .get(self.url(format!("{collection}/{key}")))
-            .send()
-            .await?;
-
-        Self::handle_response(resp).await
+        let req = self.request(Method::GET, self.key_url(collection, key)?);
+        self.fetch(req, Target::key("get", collection, key)).await
     }
 
-    pub async fn put<T: Serialize>(&self, collection: &str, key: &str, value: &T) -> Result<Value> {
-        let resp = self
-            .client
-            .put(self.url(format!("{collection}/{key}")))
-            .json(value)
-            .send()
-            .await?;
-
-        Self::handle_response(resp).await
+    /// Stores `value` under `key`.
+    ///
+    /// Keys named like a collection route (`_batch`, `_subscribe`, `_import`, `_backup`
+    /// and `_restore`) are rejected with [`Error::InvalidInput`], here and in every other
+    /// single-key method.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.put", skip_all)
+    )]
+    pub async fn put<T: Serialize>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+    ) -> Result<PutResult> {
+        let req = self
+            .request(Method::PUT, self.key_url(collection, key)?)
+            .json(value);
+        self.fetch(req, Target::key("put", collection, key)).await
     }
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.import_values", skip_all)
+    )]
     pub async fn import_values(
         &self,
         collection: &str,
         key: Option<String>,
         values: Vec<u8>,
-    ) -> Result<Value> {
-        let part = reqwest::multipart::Part::bytes(values).file_name("backup.sst");
-        let form = reqwest::multipart::Form::new().part("file", part);
+    ) -> Result<ImportReport> {
+        let values = bytes::Bytes::from(values);
+        let url = self.url(&[collection, "_import"])?;
 
         let resp = self
-            .client
-            .post(self.url(format!("{collection}/_import")))
-            .multipart(form)
-            .query(&[("key", key)])
-            .send()
+            .send_with(
+                Target::collection("import_values", collection).non_idempotent(),
+                || {
+                    let part = multipart_part(values.clone()).file_name("backup.sst");
+                    let form = reqwest::multipart::Form::new().part("file", part);
+                    Some(
+                        self.request(Method::POST, url.clone())
+                            .multipart(form)
+                            .query(&[("key", &key)]),
+                    )
+                },
+            )
             .await?;
-
         Self::handle_response(resp).await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.delete", skip_all)
+    )]
     pub async fn delete(&self, collection: &str, key: &str) -> Result<bool> {
-        Ok(self
-            .client
-            .delete(self.url(format!("{collection}/{key}")))
-            .send()
-            .await?
-            .status()
-            .is_success())
+        let req = self.request(Method::DELETE, self.key_url(collection, key)?);
+        self.check(req, Target::key("delete", collection, key))
+            .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.exists", skip_all)
+    )]
     pub async fn exists(&self, collection: &str, key: &str) -> Result<bool> {
-        Ok(self
-            .client
-            .head(self.url(format!("{collection}/{key}")))
-            .send()
-            .await?
-            .status()
-            .is_success())
+        let req = self.request(Method::HEAD, self.key_url(collection, key)?);
+        self.check(req, Target::key("exists", collection, key))
+            .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.batch_put", skip_all)
+    )]
     pub async fn batch_put<T: Serialize>(
         &self,
         collection: &str,
         items: &[BatchOperation<T>],
     ) -> Result<()> {
-        let resp = self
-            .client
-            .put(self.url(format!("{collection}/_batch")))
-            .json(&items)
-            .send()
-            .await?;
-
-        Self::handle_response::<Value>(resp).await.map(|_| ())
+        let req = self
+            .request(Method::PUT, self.url(&[collection, "_batch"])?)
+            .json(&items);
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .record_batch(collection, "batch_put", items.len());
+        self.fetch::<Value>(req, Target::collection("batch_put", collection))
+            .await
+            .map(|_| ())
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.subscribe", skip_all)
+    )]
     pub async fn subscribe(&self, collection: &str) -> Result<reqwest::Response> {
-        let resp = self
-            .client
-            .get(self.url(format!("{collection}/_subscribe")))
-            .send()
-            .await?;
-
-        match resp.status() {
-            StatusCode::OK => Ok(resp),
-            _ => Err(Error::NotFound(collection.to_string())),
-        }
+        self.open_subscription(collection, None, None).await
     }
-    pub async fn start_backup(&self, collection: &str) -> Result<Value> {
-        let resp = self
-            .client
-            .post(self.url(format!("{collection}/_backup")))
-            .send()
-            .await?;
-
-        Self::handle_response(resp).await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.start_backup", skip_all)
+    )]
+    pub async fn start_backup(&self, collection: &str) -> Result<BackupJob> {
+        let req = self.request(Method::POST, self.url(&[collection, "_backup"])?);
+        self.fetch(
+            req,
+            Target::collection("start_backup", collection).non_idempotent(),
+        )
+        .await
     }
-    pub async fn backup_status(&self, collection: &str, id: &str) -> Result<Value> {
-        let resp = self
-            .client
-            .get(self.url(format!("{collection}/_backup/status?id={id}")))
-            .send()
-            .await?;
-
-        Self::handle_response(resp).await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.backup_status", skip_all)
+    )]
+    pub async fn backup_status(&self, collection: &str, id: &str) -> Result<BackupJob> {
+        let req = self
+            .request(Method::GET, self.url(&[collection, "_backup", "status"])?)
+            .query(&[("id", id)]);
+        self.fetch(req, Target::collection("backup_status", collection))
+            .await
     }
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.download_backup", skip_all)
+    )]
     pub async fn download_backup(&self, collection: &str, backup_id: &str) -> Result<bytes::Bytes> {
-        let resp = self
-            .client
-            .get(format!(
-                "{}/backups/{collection}-{backup_id}.sst",
-                self.endpoint
-            ))
-            .send()
+        let (resp, expected) = self
+            .open_backup_download("download_backup", collection, backup_id)
             .await?;
-
-        match resp.status() {
-            StatusCode::OK => Ok(resp.bytes().await?),
-            StatusCode::NOT_FOUND => Err(Error::NotFound(backup_id.to_string())),
-            s => Err(Error::Server(format!("unexpected status: {}", s))),
+        let data = resp.bytes().await?;
+        if let Some(expected) = &expected {
+            Checksum::of(&data).verify(expected)?;
         }
+        Ok(data)
     }
-    pub async fn upload_backup(&self, collection: &str, backup_data: Vec<u8>) -> Result<Value> {
-        let part = reqwest::multipart::Part::bytes(backup_data)
-            .file_name(format!("{collection}-backup.sst"));
-
-        let form = reqwest::multipart::Form::new().part("file", part);
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.upload_backup", skip_all)
+    )]
+    pub async fn upload_backup(&self, collection: &str, backup_data: Vec<u8>) -> Result<BackupJob> {
+        let checksum = Checksum::of(&backup_data);
+        let backup_data = bytes::Bytes::from(backup_data);
+        let url = self.url(&[collection, "_backup", "upload"])?;
 
         let resp = self
-            .client
-            .post(self.url(format!("{collection}/_backup/upload")))
-            .multipart(form)
-            .send()
+            .send_with(
+                Target::collection("upload_backup", collection).non_idempotent(),
+                || {
+                    let part = multipart_part(backup_data.clone())
+                        .file_name(format!("{collection}-backup.sst"));
+                    let form = reqwest::multipart::Form::new().part("file", part);
+                    Some(
+                        self.request(Method::POST, url.clone())
+                            .header(checksum::CHECKSUM_HEADER, checksum.to_hex())
+                            .multipart(form),
+                    )
+                },
+            )
             .await?;
-
         Self::handle_response(resp).await
     }
-    pub async fn start_restore(&self, collection: &str, id: &str) -> Result<Value> {
-        let resp = self
-            .client
-            .post(self.url(format!("{collection}/_restore?backup_id={id}")))
-            .send()
-            .await?;
-
-        Self::handle_response(resp).await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.start_restore", skip_all)
+    )]
+    pub async fn start_restore(&self, collection: &str, id: &str) -> Result<RestoreJob> {
+        let req = self
+            .request(Method::POST, self.url(&[collection, "_restore"])?)
+            .query(&[("backup_id", id)]);
+        self.fetch(
+            req,
+            Target::collection("start_restore", collection).non_idempotent(),
+        )
+        .await
     }
 
-    pub async fn restore_status(&self, collection: &str, id: &str) -> Result<Value> {
-        let resp = self
-            .client
-            .get(self.url(format!("{collection}/_restore/status?id={id}")))
-            .send()
-            .await?;
-
-        Self::handle_response(resp).await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.restore_status", skip_all)
+    )]
+    pub async fn restore_status(&self, collection: &str, id: &str) -> Result<RestoreJob> {
+        let req = self
+            .request(Method::GET, self.url(&[collection, "_restore", "status"])?)
+            .query(&[("id", id)]);
+        self.fetch(req, Target::collection("restore_status", collection))
+            .await
     }
 }
+
+fn multipart_part(data: bytes::Bytes) -> reqwest::multipart::Part {
+    let len = data.len() as u64;
+    reqwest::multipart::Part::stream_with_length(reqwest::Body::from(data), len)
+}
@@ -0,0 +1,216 @@
+use crate::{
+    BatchOperation, CollectionEvent, Error, ErrorContext, EventStream, KvStore, PutResult,
+    QueryBuilder, Result, SortOrder,
+};
+use futures_util::stream::{self, StreamExt};
+use reqwest::{Method, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+/// In-process [`KvStore`] for tests, with one ordered map per collection.
+///
+/// Collections are created on first write. Queries honor `from`, `to` (both
+/// inclusive), `order`, `limit` and `keys` like the server does; JSONPath `query`
+/// expressions are not supported. Subscribers receive a [`CollectionEvent`] for every
+/// put and delete. Cloning is cheap and clones share the same data.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryKv {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    collections: HashMap<String, BTreeMap<String, Value>>,
+    subscribers: HashMap<String, Vec<UnboundedSender<CollectionEvent>>>,
+}
+
+impl State {
+    fn emit(&mut self, collection: &str, operation: &str, key: &str, value: Value) {
+        let Some(subscribers) = self.subscribers.get_mut(collection) else {
+            return;
+        };
+        let event = CollectionEvent {
+            operation: operation.to_string(),
+            key: key.to_string(),
+            value,
+            server_time: Some(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_millis() as u64),
+            ),
+        };
+        subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    fn put(&mut self, collection: &str, key: &str, value: Value) {
+        self.collections
+            .entry(collection.to_string())
+            .or_default()
+            .insert(key.to_string(), value.clone());
+        self.emit(collection, "put", key, value);
+    }
+}
+
+pub(crate) fn not_found(method: Method, collection: &str, key: Option<&str>) -> Error {
+    let path = match key {
+        Some(key) => format!("/api/{collection}/{key}"),
+        None => format!("/api/{collection}"),
+    };
+    Error::NotFound(Box::new(ErrorContext {
+        method,
+        path,
+        status: Some(StatusCode::NOT_FOUND),
+        collection: Some(collection.to_string()),
+        key: key.map(str::to_string),
+        body: None,
+    }))
+}
+
+impl MemoryKv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Nothing panics while holding the lock, but don't turn a test failure
+        // elsewhere into a poisoned store.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Keys of `collection` in order, for assertions in tests.
+    pub fn keys(&self, collection: &str) -> Vec<String> {
+        self.state()
+            .collections
+            .get(collection)
+            .map(|entries| entries.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn run_query(&self, collection: &str, query: &QueryBuilder) -> Result<Vec<Value>> {
+        if query.query.is_some() {
+            return Err(Error::InvalidInput(
+                "MemoryKv does not evaluate JSONPath queries".into(),
+            ));
+        }
+
+        let state = self.state();
+        let entries = state
+            .collections
+            .get(collection)
+            .ok_or_else(|| not_found(Method::POST, collection, None))?;
+
+        Ok(select(entries, query, Value::clone))
+    }
+}
+
+/// Applies the range, order, limit and `keys` options of `query` to `entries`, the way
+/// the server does.
+pub(crate) fn select<V>(
+    entries: &BTreeMap<String, V>,
+    query: &QueryBuilder,
+    value: impl Fn(&V) -> Value,
+) -> Vec<Value> {
+    let in_range = |key: &&String| {
+        query.from.as_ref().is_none_or(|from| *key >= from)
+            && query.to.as_ref().is_none_or(|to| *key <= to)
+    };
+    let items: Box<dyn Iterator<Item = (&String, &V)>> = match query.order {
+        Some(SortOrder::Desc) => Box::new(entries.iter().rev()),
+        _ => Box::new(entries.iter()),
+    };
+    items
+        .filter(|(key, _)| in_range(key))
+        .take(query.limit.unwrap_or(usize::MAX))
+        .map(|(key, v)| match query.keys {
+            true => json!({ "key": key, "value": value(v) }),
+            false => value(v),
+        })
+        .collect()
+}
+
+impl KvStore for MemoryKv {
+    async fn get<T: DeserializeOwned + Send>(&self, collection: &str, key: &str) -> Result<T> {
+        let value = self
+            .state()
+            .collections
+            .get(collection)
+            .and_then(|entries| entries.get(key))
+            .cloned()
+            .ok_or_else(|| not_found(Method::GET, collection, Some(key)))?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    async fn put<T: Serialize + Sync>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+    ) -> Result<PutResult> {
+        let value = serde_json::to_value(value)?;
+        self.state().put(collection, key, value);
+        Ok(PutResult {
+            key: Some(key.to_string()),
+            ..Default::default()
+        })
+    }
+
+    async fn delete(&self, collection: &str, key: &str) -> Result<bool> {
+        let mut state = self.state();
+        let removed = state
+            .collections
+            .get_mut(collection)
+            .and_then(|entries| entries.remove(key))
+            .is_some();
+        if removed {
+            state.emit(collection, "delete", key, Value::Null);
+        }
+        Ok(removed)
+    }
+
+    async fn exists(&self, collection: &str, key: &str) -> Result<bool> {
+        Ok(self
+            .state()
+            .collections
+            .get(collection)
+            .is_some_and(|entries| entries.contains_key(key)))
+    }
+
+    async fn batch_put<T: Serialize + Sync>(
+        &self,
+        collection: &str,
+        items: &[BatchOperation<T>],
+    ) -> Result<()> {
+        // Serialize everything first so a bad item leaves the store untouched.
+        let values = items
+            .iter()
+            .map(|item| Ok((item.key.as_str(), serde_json::to_value(&item.value)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut state = self.state();
+        for (key, value) in values {
+            state.put(collection, key, value);
+        }
+        Ok(())
+    }
+
+    async fn query(&self, collection: &str, query: QueryBuilder) -> Result<Vec<Value>> {
+        self.run_query(collection, &query)
+    }
+
+    fn subscribe(&self, collection: &str) -> EventStream {
+        let (tx, rx) = unbounded_channel();
+        self.state()
+            .subscribers
+            .entry(collection.to_string())
+            .or_default()
+            .push(tx);
+        stream::unfold(rx, |mut rx| async move {
+            let event = rx.recv().await?;
+            Some((Ok(event), rx))
+        })
+        .boxed()
+    }
+}
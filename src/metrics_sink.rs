@@ -0,0 +1,168 @@
+use crate::{Result, Target};
+use reqwest::{Request, Response};
+use std::time::{Duration, Instant};
+
+/// Outcome of one client call, including all of its retries.
+#[derive(Debug, Clone)]
+pub struct RequestStats<'a> {
+    pub collection: Option<&'a str>,
+    /// Name of the client method, e.g. `get`, `put_if_match` or `start_backup`. The
+    /// checksum sidecar fetched by backup downloads is recorded as `backup_checksum`.
+    pub operation: &'static str,
+    /// Status of the last response, if one was received.
+    pub status: Option<u16>,
+    pub success: bool,
+    pub retries: u32,
+    pub latency: Duration,
+    /// Body bytes sent over all attempts. Streamed uploads are not counted.
+    pub request_bytes: u64,
+    /// Body bytes of the final response, when known up front.
+    pub response_bytes: Option<u64>,
+}
+
+/// Receives client metrics, for when the `metrics` crate facade is not wanted.
+///
+/// Set one with [`SmolKvBuilder::metrics_sink`](crate::SmolKvBuilder::metrics_sink);
+/// the default is [`MetricsFacade`].
+pub trait MetricsSink: Send + Sync {
+    fn record_request(&self, stats: &RequestStats<'_>);
+
+    /// A subscription delivered an event.
+    fn record_subscribe_event(&self, collection: &str) {
+        let _ = collection;
+    }
+
+    /// A subscription is reconnecting after losing its connection.
+    fn record_reconnect(&self, collection: &str) {
+        let _ = collection;
+    }
+
+    /// A bulk operation was sent with `size` items.
+    fn record_batch(&self, collection: &str, operation: &'static str, size: usize) {
+        let _ = (collection, operation, size);
+    }
+}
+
+/// Records through the [`metrics`] crate's global recorder, labelled by `collection`
+/// and `operation`:
+///
+/// - `smolkv_requests_total` (also labelled by `status`) and `smolkv_request_errors_total`
+/// - `smolkv_request_duration_seconds` and `smolkv_request_retries_total`
+/// - `smolkv_request_bytes_total` and `smolkv_response_bytes_total`
+/// - `smolkv_subscribe_events_total` and `smolkv_subscribe_reconnects_total`
+/// - `smolkv_batch_size`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsFacade;
+
+impl MetricsSink for MetricsFacade {
+    fn record_request(&self, stats: &RequestStats<'_>) {
+        let collection = stats.collection.unwrap_or("").to_string();
+        let operation = stats.operation;
+        let status = stats
+            .status
+            .map_or_else(|| "none".to_string(), |s| s.to_string());
+
+        metrics::counter!(
+            "smolkv_requests_total",
+            "collection" => collection.clone(),
+            "operation" => operation,
+            "status" => status,
+        )
+        .increment(1);
+        if !stats.success {
+            metrics::counter!(
+                "smolkv_request_errors_total",
+                "collection" => collection.clone(),
+                "operation" => operation,
+            )
+            .increment(1);
+        }
+        metrics::histogram!(
+            "smolkv_request_duration_seconds",
+            "collection" => collection.clone(),
+            "operation" => operation,
+        )
+        .record(stats.latency.as_secs_f64());
+        metrics::counter!(
+            "smolkv_request_retries_total",
+            "collection" => collection.clone(),
+            "operation" => operation,
+        )
+        .increment(u64::from(stats.retries));
+        metrics::counter!(
+            "smolkv_request_bytes_total",
+            "collection" => collection.clone(),
+            "operation" => operation,
+        )
+        .increment(stats.request_bytes);
+        if let Some(bytes) = stats.response_bytes {
+            metrics::counter!(
+                "smolkv_response_bytes_total",
+                "collection" => collection,
+                "operation" => operation,
+            )
+            .increment(bytes);
+        }
+    }
+
+    fn record_subscribe_event(&self, collection: &str) {
+        metrics::counter!("smolkv_subscribe_events_total", "collection" => collection.to_string())
+            .increment(1);
+    }
+
+    fn record_reconnect(&self, collection: &str) {
+        metrics::counter!("smolkv_subscribe_reconnects_total", "collection" => collection.to_string())
+            .increment(1);
+    }
+
+    fn record_batch(&self, collection: &str, operation: &'static str, size: usize) {
+        metrics::histogram!(
+            "smolkv_batch_size",
+            "collection" => collection.to_string(),
+            "operation" => operation,
+        )
+        .record(size as f64);
+    }
+}
+
+/// Collects the [`RequestStats`] of one call across its attempts.
+pub(crate) struct RequestTimer {
+    started: Instant,
+    attempts: u32,
+    request_bytes: u64,
+}
+
+impl RequestTimer {
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            attempts: 0,
+            request_bytes: 0,
+        }
+    }
+
+    pub fn start_attempt(&mut self, request: &Request) {
+        self.attempts += 1;
+        self.request_bytes += request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map_or(0, |bytes| bytes.len() as u64);
+    }
+
+    pub fn finish(&self, sink: &dyn MetricsSink, target: Target<'_>, result: &Result<Response>) {
+        let (status, response_bytes) = match result {
+            Ok(resp) => (Some(resp.status().as_u16()), resp.content_length()),
+            Err(e) => (e.context().and_then(|c| c.status).map(|s| s.as_u16()), None),
+        };
+        sink.record_request(&RequestStats {
+            collection: target.collection,
+            operation: target.operation,
+            status,
+            success: result.is_ok(),
+            retries: self.attempts.saturating_sub(1),
+            latency: self.started.elapsed(),
+            request_bytes: self.request_bytes,
+            response_bytes,
+        });
+    }
+}
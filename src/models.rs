@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// State of a server-side backup or restore job.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    #[default]
+    Pending,
+    #[serde(alias = "in_progress", alias = "started")]
+    Running,
+    #[serde(alias = "complete", alias = "done", alias = "success")]
+    Completed,
+    #[serde(alias = "error", alias = "failure")]
+    Failed,
+    /// A state this client version does not know about.
+    #[serde(other)]
+    Unknown,
+}
+
+impl JobState {
+    /// Whether the job has stopped and its state will not change anymore.
+    pub fn is_terminal(self) -> bool {
+        matches!(self, JobState::Completed | JobState::Failed)
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct CollectionInfo {
+    #[serde(default, alias = "collection")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct PutResult {
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ImportFailure {
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default, alias = "error")]
+    pub reason: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ImportReport {
+    #[serde(default, alias = "count")]
+    pub imported: u64,
+    #[serde(default)]
+    pub failed: u64,
+    #[serde(default, alias = "errors")]
+    pub failures: Vec<ImportFailure>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct BackupJob {
+    /// `None` if the server did not return one.
+    #[serde(default, alias = "backup_id")]
+    pub id: Option<String>,
+    /// Required, so a malformed reply fails instead of looking like a pending job.
+    pub status: JobState,
+    #[serde(default)]
+    pub started_at: Option<u64>,
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct RestoreJob {
+    /// `None` if the server did not return one.
+    #[serde(default, alias = "restore_id")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub backup_id: Option<String>,
+    /// Required, so a malformed reply fails instead of looking like a pending job.
+    pub status: JobState,
+    #[serde(default)]
+    pub started_at: Option<u64>,
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
@@ -0,0 +1,125 @@
+use crate::{Collection, QueryBuilder, Result, SmolKv, SortOrder};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::VecDeque;
+
+/// Item returned by the server for queries with `keys(true)`.
+#[derive(Debug, Deserialize)]
+struct KeyedItem<T> {
+    key: String,
+    value: T,
+}
+
+struct Pager<T> {
+    kv: SmolKv,
+    collection: String,
+    query: QueryBuilder,
+    page_size: usize,
+    remaining: Option<usize>,
+    last_key: Option<String>,
+    buffer: VecDeque<(String, T)>,
+    done: bool,
+}
+
+impl<T: DeserializeOwned> Pager<T> {
+    fn descending(&self) -> bool {
+        matches!(self.query.order, Some(SortOrder::Desc))
+    }
+
+    /// Whether `key` lies strictly past the last key already yielded.
+    fn is_new(&self, key: &str) -> bool {
+        match &self.last_key {
+            None => true,
+            Some(last) if self.descending() => key < last.as_str(),
+            Some(last) => key > last.as_str(),
+        }
+    }
+
+    async fn fetch_page(&mut self) -> Result<()> {
+        let mut query = self.query.clone().keys(true);
+        // Range bounds are inclusive, so continuation pages repeat the last key.
+        let limit = self.page_size + usize::from(self.last_key.is_some());
+        query.limit = Some(limit);
+        if let Some(last) = &self.last_key {
+            if self.descending() {
+                query.to = Some(last.clone());
+            } else {
+                query.from = Some(last.clone());
+            }
+        }
+
+        let page = self.kv.query_collection(&self.collection, query).await?;
+        let fetched = page.len();
+        for item in page {
+            let item: KeyedItem<T> = serde_json::from_value(item)?;
+            if self.is_new(&item.key) {
+                self.last_key = Some(item.key.clone());
+                self.buffer.push_back((item.key, item.value));
+            }
+        }
+
+        if fetched < limit || self.buffer.is_empty() {
+            self.done = true;
+        }
+        Ok(())
+    }
+
+    async fn next_item(&mut self) -> Option<Result<(String, T)>> {
+        loop {
+            if self.remaining == Some(0) {
+                return None;
+            }
+            if let Some(item) = self.buffer.pop_front() {
+                if let Some(remaining) = &mut self.remaining {
+                    *remaining -= 1;
+                }
+                return Some(Ok(item));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(e) = self.fetch_page().await {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+impl SmolKv {
+    /// Streams every `(key, value)` pair matching `query`, fetching `page_size` items
+    /// at a time. The query's `limit`, if any, caps the total number of items.
+    pub fn query_stream<T: DeserializeOwned + Send + 'static>(
+        &self,
+        collection: &str,
+        query: QueryBuilder,
+        page_size: usize,
+    ) -> BoxStream<'static, Result<(String, T)>> {
+        let pager = Pager {
+            kv: self.clone(),
+            collection: collection.to_string(),
+            remaining: query.limit,
+            query,
+            page_size: page_size.max(1),
+            last_key: None,
+            buffer: VecDeque::new(),
+            done: false,
+        };
+
+        stream::unfold(pager, |mut pager| async move {
+            let item = pager.next_item().await?;
+            Some((item, pager))
+        })
+        .boxed()
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Collection<T> {
+    pub fn query_stream(
+        &self,
+        query: QueryBuilder,
+        page_size: usize,
+    ) -> BoxStream<'static, Result<(String, T)>> {
+        self.kv().query_stream(self.name(), query, page_size)
+    }
+}
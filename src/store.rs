@@ -0,0 +1,87 @@
+use crate::{BatchOperation, EventStream, PutResult, QueryBuilder, Result, SmolKv};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::future::Future;
+
+/// The key-value operations applications usually need, so code can be written against
+/// either a real server ([`SmolKv`]) or an in-process store ([`MemoryKv`](crate::MemoryKv)).
+pub trait KvStore: Send + Sync {
+    fn get<T: DeserializeOwned + Send>(
+        &self,
+        collection: &str,
+        key: &str,
+    ) -> impl Future<Output = Result<T>> + Send;
+
+    fn put<T: Serialize + Sync>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+    ) -> impl Future<Output = Result<PutResult>> + Send;
+
+    /// Returns whether the key existed.
+    fn delete(&self, collection: &str, key: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    fn exists(&self, collection: &str, key: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    fn batch_put<T: Serialize + Sync>(
+        &self,
+        collection: &str,
+        items: &[BatchOperation<T>],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn query(
+        &self,
+        collection: &str,
+        query: QueryBuilder,
+    ) -> impl Future<Output = Result<Vec<Value>>> + Send;
+
+    fn subscribe(&self, collection: &str) -> EventStream;
+}
+
+impl KvStore for SmolKv {
+    fn get<T: DeserializeOwned + Send>(
+        &self,
+        collection: &str,
+        key: &str,
+    ) -> impl Future<Output = Result<T>> + Send {
+        SmolKv::get(self, collection, key)
+    }
+
+    fn put<T: Serialize + Sync>(
+        &self,
+        collection: &str,
+        key: &str,
+        value: &T,
+    ) -> impl Future<Output = Result<PutResult>> + Send {
+        SmolKv::put(self, collection, key, value)
+    }
+
+    fn delete(&self, collection: &str, key: &str) -> impl Future<Output = Result<bool>> + Send {
+        SmolKv::delete(self, collection, key)
+    }
+
+    fn exists(&self, collection: &str, key: &str) -> impl Future<Output = Result<bool>> + Send {
+        SmolKv::exists(self, collection, key)
+    }
+
+    fn batch_put<T: Serialize + Sync>(
+        &self,
+        collection: &str,
+        items: &[BatchOperation<T>],
+    ) -> impl Future<Output = Result<()>> + Send {
+        SmolKv::batch_put(self, collection, items)
+    }
+
+    fn query(
+        &self,
+        collection: &str,
+        query: QueryBuilder,
+    ) -> impl Future<Output = Result<Vec<Value>>> + Send {
+        self.query_collection(collection, query)
+    }
+
+    fn subscribe(&self, collection: &str) -> EventStream {
+        self.subscribe_events(collection)
+    }
+}
@@ -0,0 +1,439 @@
+use crate::events::{SseEvent, SseParser};
+use crate::{Error, Layer, Result, SharedTransport, SmolKv, Transport};
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use reqwest::header::HeaderMap;
+use reqwest::{Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Request headers that are not recorded: credentials and per-connection details.
+const SKIPPED_REQUEST_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "x-secret-key",
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+];
+/// Response headers that are not recorded or replayed: session cookies, and framing
+/// headers that no longer fit once the body is re-encoded.
+const SKIPPED_RESPONSE_HEADERS: &[&str] = &[
+    "content-length",
+    "transfer-encoding",
+    "connection",
+    "set-cookie",
+];
+
+/// How a replaying [`Cassette`] matches requests to recorded interactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Requests must arrive in the recorded order, with the same method, path, query
+    /// and body.
+    Strict,
+    /// Requests may arrive in any order and only need the same method, path and query.
+    Lenient,
+}
+
+/// A request or response body. JSON bodies stay readable in the cassette file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedBody {
+    Json(Value),
+    Text(String),
+    Hex(String),
+}
+
+impl RecordedBody {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.is_empty() {
+            return None;
+        }
+        if let Ok(value) = serde_json::from_slice(bytes) {
+            return Some(Self::Json(value));
+        }
+        Some(match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Hex(hex::encode(bytes)),
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Json(value) => value.to_string().into_bytes(),
+            Self::Text(text) => text.clone().into_bytes(),
+            Self::Hex(data) => hex::decode(data).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CassetteRequest {
+    method: String,
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<RecordedBody>,
+}
+
+impl CassetteRequest {
+    fn describe(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{} {}?{query}", self.method, self.path),
+            None => format!("{} {}", self.method, self.path),
+        }
+    }
+
+    /// Multipart boundaries are random, so those bodies are never compared.
+    fn is_multipart(&self) -> bool {
+        self.headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("content-type") && value.starts_with("multipart/")
+        })
+    }
+
+    fn matches(&self, other: &CassetteRequest, mode: ReplayMode) -> bool {
+        let same_target =
+            self.method == other.method && self.path == other.path && self.query == other.query;
+        match mode {
+            ReplayMode::Lenient => same_target,
+            ReplayMode::Strict => same_target && (self.is_multipart() || self.body == other.body),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CassetteResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<RecordedBody>,
+    /// Frames of a `text/event-stream` response, in place of a body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    events: Option<Vec<SseEvent>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: CassetteRequest,
+    response: CassetteResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Tape {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Record,
+    Replay(ReplayMode),
+}
+
+#[derive(Debug, Default)]
+struct Playback {
+    tape: Tape,
+    used: Vec<bool>,
+    unmatched: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    mode: Mode,
+    playback: Mutex<Playback>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Playback> {
+        self.playback.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Records a client's HTTP traffic to a JSON file and replays it later, for
+/// deterministic tests without a server.
+///
+/// A cassette is a [`Layer`]: add it to a client with
+/// [`SmolKvBuilder::layer`](crate::SmolKvBuilder::layer), or use [`Cassette::client`].
+/// While recording, requests go through the inner transport and every
+/// request/response pair is kept, including the frames of `_subscribe` streams;
+/// [`Cassette::save`] writes them out. While replaying, requests are answered from the
+/// file without any network access, and requests without a matching interaction fail
+/// with an [`Error::Io`]. Requests are matched by path, so replay with the same
+/// endpoint path as the recording; the host is never contacted. Secret keys,
+/// `Authorization`, `Cookie` and `Set-Cookie` headers and streamed request bodies are
+/// never recorded.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), smolkv_client::Error> {
+/// use smolkv_client::testing::{Cassette, ReplayMode};
+///
+/// let cassette = Cassette::record();
+/// cassette.client("http://localhost:5050").put("users", "bob", &42).await?;
+/// cassette.save("tests/fixtures/users.json")?;
+///
+/// let cassette = Cassette::replay("tests/fixtures/users.json", ReplayMode::Strict)?;
+/// cassette.client("http://localhost:5050").put("users", "bob", &42).await?;
+/// cassette.assert_done();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    shared: Arc<Shared>,
+}
+
+impl Cassette {
+    /// Starts an empty cassette that records what passes through it.
+    pub fn record() -> Self {
+        Self::new(Mode::Record, Tape::default())
+    }
+
+    /// Replays the interactions saved at `path`.
+    pub fn replay(path: impl AsRef<Path>, mode: ReplayMode) -> Result<Self> {
+        let tape = serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(Self::new(Mode::Replay(mode), tape))
+    }
+
+    fn new(mode: Mode, tape: Tape) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                mode,
+                playback: Mutex::new(Playback {
+                    used: vec![false; tape.interactions.len()],
+                    tape,
+                    unmatched: Vec::new(),
+                }),
+            }),
+        }
+    }
+
+    /// A client for `endpoint` whose requests go through this cassette, without a
+    /// secret.
+    ///
+    /// # Panics
+    ///
+    /// If `endpoint` is not a valid endpoint URL.
+    pub fn client(&self, endpoint: impl Into<String>) -> SmolKv {
+        SmolKv::builder(endpoint)
+            .layer(self.clone())
+            .build()
+            .expect("valid cassette endpoint")
+    }
+
+    /// Writes the recorded interactions to `path` as pretty-printed JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_vec_pretty(&self.shared.lock().tape)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Requests that had no matching interaction during replay.
+    pub fn unmatched(&self) -> Vec<String> {
+        self.shared.lock().unmatched.clone()
+    }
+
+    /// Checks that every request was matched and, in strict mode, that every recorded
+    /// interaction was replayed.
+    ///
+    /// # Panics
+    ///
+    /// If either check fails, listing the offending requests.
+    pub fn assert_done(&self) {
+        let playback = self.shared.lock();
+        assert!(
+            playback.unmatched.is_empty(),
+            "requests without a recorded interaction: {:?}",
+            playback.unmatched
+        );
+        if matches!(self.shared.mode, Mode::Replay(ReplayMode::Strict)) {
+            let unused: Vec<_> = playback
+                .tape
+                .interactions
+                .iter()
+                .zip(&playback.used)
+                .filter(|(_, used)| !**used)
+                .map(|(interaction, _)| interaction.request.describe())
+                .collect();
+            assert!(unused.is_empty(), "interactions never replayed: {unused:?}");
+        }
+    }
+}
+
+impl Layer for Cassette {
+    /// Records through `inner`, or replays without ever using it.
+    fn layer(&self, inner: SharedTransport) -> SharedTransport {
+        Arc::new(CassetteTransport {
+            inner,
+            shared: self.shared.clone(),
+        })
+    }
+}
+
+struct CassetteTransport {
+    inner: SharedTransport,
+    shared: Arc<Shared>,
+}
+
+impl Transport for CassetteTransport {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let recorded = CassetteRequest {
+                method: request.method().to_string(),
+                path: request.url().path().to_string(),
+                query: request.url().query().map(str::to_string),
+                headers: headers(request.headers(), SKIPPED_REQUEST_HEADERS),
+                body: request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .and_then(RecordedBody::from_bytes),
+            };
+            match self.shared.mode {
+                Mode::Record => {
+                    record(&self.shared, recorded, self.inner.send(request).await?).await
+                }
+                Mode::Replay(mode) => replay(&self.shared, recorded, mode),
+            }
+        })
+    }
+}
+
+fn headers(headers: &HeaderMap, skipped: &[&str]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(name, _)| !skipped.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn response(status: u16, headers: &[(String, String)], body: reqwest::Body) -> Response {
+    let mut builder = http::Response::builder().status(status);
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    builder.body(body).expect("valid response").into()
+}
+
+async fn record(
+    shared: &Arc<Shared>,
+    request: CassetteRequest,
+    resp: Response,
+) -> Result<Response> {
+    let status = resp.status().as_u16();
+    let response_headers = headers(resp.headers(), SKIPPED_RESPONSE_HEADERS);
+    let streaming = response_headers
+        .iter()
+        .any(|(name, value)| name == "content-type" && value.starts_with("text/event-stream"));
+
+    if !streaming {
+        let body = resp.bytes().await?;
+        shared.lock().tape.interactions.push(Interaction {
+            request,
+            response: CassetteResponse {
+                status,
+                headers: response_headers.clone(),
+                body: RecordedBody::from_bytes(&body),
+                events: None,
+            },
+        });
+        return Ok(response(status, &response_headers, body.into()));
+    }
+
+    // Record the interaction up front and append frames as they pass through, so a
+    // stream that never ends is still saved.
+    let index = {
+        let mut playback = shared.lock();
+        playback.tape.interactions.push(Interaction {
+            request,
+            response: CassetteResponse {
+                status,
+                headers: response_headers.clone(),
+                body: None,
+                events: Some(Vec::new()),
+            },
+        });
+        playback.tape.interactions.len() - 1
+    };
+    let shared = shared.clone();
+    let mut parser = SseParser::default();
+    let stream = resp.bytes_stream().inspect(move |chunk| {
+        if let Ok(chunk) = chunk {
+            let frames = parser.push(chunk);
+            let mut playback = shared.lock();
+            if let Some(events) = &mut playback.tape.interactions[index].response.events {
+                events.extend(frames);
+            }
+        }
+    });
+    Ok(response(
+        status,
+        &response_headers,
+        reqwest::Body::wrap_stream(stream),
+    ))
+}
+
+fn replay(shared: &Shared, request: CassetteRequest, mode: ReplayMode) -> Result<Response> {
+    let mut playback = shared.lock();
+    let Playback {
+        tape,
+        used,
+        unmatched,
+        ..
+    } = &mut *playback;
+
+    let candidate = match mode {
+        ReplayMode::Strict => used.iter().position(|used| !used),
+        ReplayMode::Lenient => tape
+            .interactions
+            .iter()
+            .zip(used.iter())
+            .position(|(interaction, used)| !used && interaction.request.matches(&request, mode)),
+    };
+    let Some(index) = candidate.filter(|&i| tape.interactions[i].request.matches(&request, mode))
+    else {
+        let description = request.describe();
+        unmatched.push(description.clone());
+        return Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("cassette has no interaction for {description}"),
+        )));
+    };
+    used[index] = true;
+
+    let recorded = &tape.interactions[index].response;
+    let body = match &recorded.events {
+        Some(events) => events
+            .iter()
+            .map(encode_event)
+            .collect::<String>()
+            .into_bytes(),
+        None => recorded
+            .body
+            .as_ref()
+            .map(RecordedBody::to_bytes)
+            .unwrap_or_default(),
+    };
+    Ok(response(recorded.status, &recorded.headers, body.into()))
+}
+
+fn encode_event(event: &SseEvent) -> String {
+    let mut frame = String::new();
+    if let Some(name) = &event.event {
+        frame.push_str(&format!("event: {name}\n"));
+    }
+    if let Some(id) = &event.id {
+        frame.push_str(&format!("id: {id}\n"));
+    }
+    if let Some(retry) = event.retry {
+        frame.push_str(&format!("retry: {retry}\n"));
+    }
+    for line in event.data.split('\n').filter(|_| !event.data.is_empty()) {
+        frame.push_str(&format!("data: {line}\n"));
+    }
+    frame.push('\n');
+    frame
+}
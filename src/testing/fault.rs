@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+/// A failure the [`MockServer`](super::MockServer) injects into matching requests.
+///
+/// Faults apply to every request by default; narrow them down with
+/// [`Fault::method`] and [`Fault::path`], and limit how often they fire with
+/// [`Fault::times`].
+///
+/// ```
+/// use smolkv_client::testing::Fault;
+///
+/// // The first two writes to `users` fail with 503 and a one second Retry-After.
+/// let fault = Fault::status(503)
+///     .retry_after(1)
+///     .method("PUT")
+///     .path("/api/users")
+///     .times(2);
+/// # let _ = fault;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fault {
+    pub(crate) kind: FaultKind,
+    method: Option<String>,
+    path: Option<String>,
+    pub(crate) remaining: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum FaultKind {
+    Status {
+        status: u16,
+        retry_after: Option<u64>,
+    },
+    Latency(Duration),
+    DropStream {
+        after: usize,
+    },
+}
+
+impl Fault {
+    fn new(kind: FaultKind) -> Self {
+        Self {
+            kind,
+            method: None,
+            path: None,
+            remaining: None,
+        }
+    }
+
+    /// Answers with `status` instead of handling the request.
+    pub fn status(status: u16) -> Self {
+        Self::new(FaultKind::Status {
+            status,
+            retry_after: None,
+        })
+    }
+
+    /// Delays the request before handling it.
+    pub fn latency(delay: Duration) -> Self {
+        Self::new(FaultKind::Latency(delay))
+    }
+
+    /// Closes `_subscribe` streams after sending `after` events.
+    pub fn drop_stream(after: usize) -> Self {
+        Self::new(FaultKind::DropStream { after })
+    }
+
+    /// Adds a `Retry-After` header, in seconds, to a [`Fault::status`] response.
+    pub fn retry_after(mut self, secs: u64) -> Self {
+        if let FaultKind::Status { retry_after, .. } = &mut self.kind {
+            *retry_after = Some(secs);
+        }
+        self
+    }
+
+    /// Only matches requests with this HTTP method.
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = Some(method.to_ascii_uppercase());
+        self
+    }
+
+    /// Only matches requests whose percent-encoded path starts with `prefix`.
+    pub fn path(mut self, prefix: impl Into<String>) -> Self {
+        self.path = Some(prefix.into());
+        self
+    }
+
+    /// Fires for the first `n` matching requests only.
+    pub fn times(mut self, n: usize) -> Self {
+        self.remaining = Some(n);
+        self
+    }
+
+    pub(crate) fn matches(&self, method: &str, path: &str) -> bool {
+        self.method.as_deref().is_none_or(|m| m == method)
+            && self.path.as_deref().is_none_or(|p| path.starts_with(p))
+            && self.remaining != Some(0)
+    }
+}
+
+/// Takes one use of every fault matching the request that `select` accepts.
+pub(crate) fn take_matching<T>(
+    faults: &mut Vec<Fault>,
+    method: &str,
+    path: &str,
+    select: impl Fn(&FaultKind) -> Option<T>,
+) -> Vec<T> {
+    let mut taken = Vec::new();
+    for fault in faults.iter_mut() {
+        if !fault.matches(method, path) {
+            continue;
+        }
+        if let Some(value) = select(&fault.kind) {
+            if let Some(remaining) = &mut fault.remaining {
+                *remaining -= 1;
+            }
+            taken.push(value);
+        }
+    }
+    faults.retain(|fault| fault.remaining != Some(0));
+    taken
+}
@@ -0,0 +1,13 @@
+//! Test support, behind the `testing` feature.
+//!
+//! [`MockServer`] runs an in-memory SmolKV server inside the test process, so
+//! integration tests exercise the real HTTP client without an external server.
+//! [`Cassette`] records a client's traffic to a file and replays it later.
+
+mod cassette;
+mod fault;
+mod server;
+
+pub use cassette::{Cassette, ReplayMode};
+pub use fault::Fault;
+pub use server::{MockServer, RecordedRequest};
@@ -0,0 +1,905 @@
+use super::fault::{take_matching, Fault, FaultKind};
+use crate::memory::select;
+use crate::{BatchOperation, Checksum, QueryBuilder, SmolKv};
+use axum::body::Bytes;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::header::{CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH, RETRY_AFTER};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// Reconnect delay the mock suggests to subscribers, short to keep tests fast.
+const RECONNECT_HINT: Duration = Duration::from_millis(50);
+
+/// A request the [`MockServer`] received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Percent-encoded path, without the query string.
+    pub path: String,
+    pub query: Option<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+    version: u64,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+
+    fn expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Instant::now())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Collection {
+    entries: BTreeMap<String, Entry>,
+    /// Every event sent to subscribers, for resuming from `Last-Event-ID` or `since`.
+    events: Vec<(u64, String)>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    collections: BTreeMap<String, Collection>,
+    /// Backup files by name, e.g. `users-3.sst`.
+    backups: HashMap<String, Bytes>,
+    backup_jobs: HashMap<(String, String), Value>,
+    restore_jobs: HashMap<(String, String), Value>,
+    faults: Vec<Fault>,
+    requests: Vec<RecordedRequest>,
+    next_id: u64,
+}
+
+/// Event published to live subscribers: collection, event id and JSON payload.
+type Published = (String, u64, String);
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<MockState>,
+    events: broadcast::Sender<Published>,
+}
+
+type AppState = Arc<Shared>;
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl MockState {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// The collection's live entries, dropping the expired ones first.
+    fn collection(&mut self, name: &str) -> Option<&mut Collection> {
+        let collection = self.collections.get_mut(name)?;
+        collection.entries.retain(|_, entry| !entry.expired());
+        Some(collection)
+    }
+
+    fn entry(&mut self, collection: &str, key: &str) -> Option<&Entry> {
+        self.collection(collection)?.entries.get(key)
+    }
+
+    fn emit(
+        &mut self,
+        events: &broadcast::Sender<Published>,
+        collection: &str,
+        operation: &str,
+        key: &str,
+        value: Value,
+    ) {
+        let id = self.next_id();
+        let data = json!({
+            "operation": operation,
+            "key": key,
+            "value": value,
+            "server_time": id,
+        })
+        .to_string();
+        self.collections
+            .entry(collection.to_string())
+            .or_default()
+            .events
+            .push((id, data.clone()));
+        let _ = events.send((collection.to_string(), id, data));
+    }
+
+    fn put(
+        &mut self,
+        events: &broadcast::Sender<Published>,
+        collection: &str,
+        key: &str,
+        value: Value,
+        ttl: Option<u64>,
+    ) -> String {
+        let version = self.next_id();
+        let entry = Entry {
+            value: value.clone(),
+            version,
+            expires_at: ttl.map(|secs| Instant::now() + Duration::from_secs(secs)),
+        };
+        let etag = entry.etag();
+        self.collections
+            .entry(collection.to_string())
+            .or_default()
+            .entries
+            .insert(key.to_string(), entry);
+        self.emit(events, collection, "put", key, value);
+        etag
+    }
+
+    fn delete(
+        &mut self,
+        events: &broadcast::Sender<Published>,
+        collection: &str,
+        key: &str,
+    ) -> bool {
+        let removed = self
+            .collection(collection)
+            .and_then(|c| c.entries.remove(key))
+            .is_some();
+        if removed {
+            self.emit(events, collection, "delete", key, Value::Null);
+        }
+        removed
+    }
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(json!({ "error": message.into() }))).into_response()
+}
+
+fn not_found(what: &str) -> Response {
+    error(StatusCode::NOT_FOUND, format!("{what} not found"))
+}
+
+/// In-process SmolKV server for integration tests, holding all data in memory.
+///
+/// It serves the key, collection, batch, `_subscribe`, `_import`, backup and restore
+/// endpoints and the `/backups/*.sst` files. Keys carry versions for conditional
+/// writes, and [`Fault`]s can make requests fail, slow down or drop streams.
+/// Backup and restore jobs complete immediately. Collections are created on first
+/// write. The server stops when dropped.
+///
+/// ```
+/// # tokio_test_block_on(async {
+/// use smolkv_client::testing::MockServer;
+///
+/// let server = MockServer::start().await;
+/// let kv = server.client();
+/// kv.put("users", "bob", &serde_json::json!({ "age": 42 })).await?;
+/// assert_eq!(server.keys("users"), ["bob"]);
+/// # Ok::<(), smolkv_client::Error>(())
+/// # });
+/// # fn tokio_test_block_on<F: std::future::Future>(f: F) -> F::Output {
+/// #     tokio::runtime::Runtime::new().unwrap().block_on(f)
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    shared: AppState,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts the server on a random local port.
+    ///
+    /// # Panics
+    ///
+    /// If no local port can be bound.
+    pub async fn start() -> Self {
+        let (events, _) = broadcast::channel(1024);
+        let shared = Arc::new(Shared {
+            state: Mutex::default(),
+            events,
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind mock server");
+        let addr = listener.local_addr().expect("mock server has no address");
+        let app = router(shared.clone());
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Self { addr, shared, task }
+    }
+
+    /// Base URL to point a client at.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A client for this server without a secret.
+    pub fn client(&self) -> SmolKv {
+        SmolKv::new(self.url(), None::<String>).expect("mock server URL is valid")
+    }
+
+    pub fn inject(&self, fault: Fault) {
+        self.shared.lock().faults.push(fault);
+    }
+
+    pub fn clear_faults(&self) {
+        self.shared.lock().faults.clear();
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.shared.lock().requests.clone()
+    }
+
+    /// Live keys of `collection`, in order.
+    pub fn keys(&self, collection: &str) -> Vec<String> {
+        self.shared
+            .lock()
+            .collection(collection)
+            .map(|c| c.entries.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn value(&self, collection: &str, key: &str) -> Option<Value> {
+        self.shared
+            .lock()
+            .entry(collection, key)
+            .map(|entry| entry.value.clone())
+    }
+
+    /// Stores a value directly, notifying subscribers like a client write would.
+    pub fn insert(&self, collection: &str, key: &str, value: Value) {
+        let shared = &self.shared;
+        shared
+            .lock()
+            .put(&shared.events, collection, key, value, None);
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn router(shared: AppState) -> Router {
+    Router::new()
+        .route(
+            "/api/:collection",
+            get(list_collection)
+                .head(collection_exists)
+                .post(query_collection)
+                .put(create_collection)
+                .delete(drop_collection),
+        )
+        .route(
+            "/api/:collection/:key",
+            get(get_key).head(head_key).put(put_key).delete(delete_key),
+        )
+        .route("/api/:collection/_batch", put(batch_put))
+        .route("/api/:collection/_batch/:op", post(batch))
+        .route("/api/:collection/_subscribe", get(subscribe))
+        .route("/api/:collection/_import", post(import))
+        .route("/api/:collection/_backup", post(start_backup))
+        .route("/api/:collection/_backup/status", get(backup_status))
+        .route("/api/:collection/_backup/upload", post(upload_backup))
+        .route("/api/:collection/_restore", post(start_restore))
+        .route("/api/:collection/_restore/status", get(restore_status))
+        .route("/backups/:file", get(backup_file))
+        .layer(middleware::from_fn_with_state(
+            shared.clone(),
+            inject_faults,
+        ))
+        .with_state(shared)
+}
+
+async fn inject_faults(State(shared): State<AppState>, req: Request, next: Next) -> Response {
+    let method = req.method().as_str().to_string();
+    let path = req.uri().path().to_string();
+
+    let (latencies, statuses) = {
+        let mut state = shared.lock();
+        state.requests.push(RecordedRequest {
+            method: method.clone(),
+            path: path.clone(),
+            query: req.uri().query().map(str::to_string),
+        });
+        let latencies = take_matching(&mut state.faults, &method, &path, |kind| match kind {
+            FaultKind::Latency(delay) => Some(*delay),
+            _ => None,
+        });
+        let statuses = take_matching(&mut state.faults, &method, &path, |kind| match kind {
+            FaultKind::Status {
+                status,
+                retry_after,
+            } => Some((*status, *retry_after)),
+            _ => None,
+        });
+        (latencies, statuses)
+    };
+
+    for delay in latencies {
+        tokio::time::sleep(delay).await;
+    }
+    if let Some((status, retry_after)) = statuses.first() {
+        let status = StatusCode::from_u16(*status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut resp = error(status, "injected fault");
+        if let Some(secs) = retry_after {
+            resp.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(*secs));
+        }
+        return resp;
+    }
+    next.run(req).await
+}
+
+// collections
+
+fn run_query(shared: &Shared, collection: &str, query: QueryBuilder) -> Response {
+    if query.query.is_some() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "JSONPath queries are not supported by the mock server",
+        );
+    }
+    let mut state = shared.lock();
+    match state.collection(collection) {
+        Some(c) => Json(select(&c.entries, &query, |entry| entry.value.clone())).into_response(),
+        None => not_found("collection"),
+    }
+}
+
+async fn list_collection(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    Query(query): Query<QueryBuilder>,
+) -> Response {
+    run_query(&shared, &collection, query)
+}
+
+async fn query_collection(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    Json(query): Json<QueryBuilder>,
+) -> Response {
+    run_query(&shared, &collection, query)
+}
+
+async fn collection_exists(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+) -> StatusCode {
+    match shared.lock().collections.contains_key(&collection) {
+        true => StatusCode::OK,
+        false => StatusCode::NOT_FOUND,
+    }
+}
+
+async fn create_collection(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+) -> Response {
+    let mut state = shared.lock();
+    if state.collections.contains_key(&collection) {
+        return error(StatusCode::CONFLICT, "collection already exists");
+    }
+    state
+        .collections
+        .insert(collection.clone(), Collection::default());
+    Json(json!({ "name": collection, "message": "collection created" })).into_response()
+}
+
+async fn drop_collection(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+) -> Response {
+    match shared.lock().collections.remove(&collection) {
+        Some(_) => {
+            Json(json!({ "name": collection, "message": "collection dropped" })).into_response()
+        }
+        None => not_found("collection"),
+    }
+}
+
+// keys
+
+async fn get_key(
+    State(shared): State<AppState>,
+    Path((collection, key)): Path<(String, String)>,
+) -> Response {
+    match shared.lock().entry(&collection, &key) {
+        Some(entry) => ([(ETAG, entry.etag())], Json(entry.value.clone())).into_response(),
+        None => not_found("key"),
+    }
+}
+
+async fn head_key(
+    State(shared): State<AppState>,
+    Path((collection, key)): Path<(String, String)>,
+) -> Response {
+    let mut state = shared.lock();
+    let Some(entry) = state.entry(&collection, &key) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        ETAG,
+        HeaderValue::from_str(&entry.etag()).expect("etag is ascii"),
+    );
+    if let Some(at) = entry.expires_at {
+        let secs = at.saturating_duration_since(Instant::now()).as_secs();
+        headers.insert("x-ttl", HeaderValue::from(secs));
+    }
+    (headers, StatusCode::OK).into_response()
+}
+
+/// Whether the `If-Match`/`If-None-Match` headers allow writing over `current`.
+fn precondition_holds(headers: &HeaderMap, current: Option<&Entry>) -> bool {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|v: &HeaderValue| v.to_str().ok())
+    };
+    if header(IF_NONE_MATCH) == Some("*") && current.is_some() {
+        return false;
+    }
+    match (header(IF_MATCH), current) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some("*"), Some(_)) => true,
+        (Some(tag), Some(entry)) => tag == entry.etag(),
+    }
+}
+
+async fn put_key(
+    State(shared): State<AppState>,
+    Path((collection, key)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Ok(value) = serde_json::from_slice::<Value>(&body) else {
+        return error(StatusCode::BAD_REQUEST, "body is not valid JSON");
+    };
+    let ttl = match params.get("ttl").map(|ttl| ttl.parse::<u64>()) {
+        None => None,
+        Some(Ok(ttl)) => Some(ttl),
+        Some(Err(_)) => return error(StatusCode::BAD_REQUEST, "ttl must be a number of seconds"),
+    };
+
+    let mut state = shared.lock();
+    if !precondition_holds(&headers, state.entry(&collection, &key)) {
+        return error(StatusCode::PRECONDITION_FAILED, "precondition failed");
+    }
+    let etag = state.put(&shared.events, &collection, &key, value, ttl);
+    (
+        [(ETAG, etag)],
+        Json(json!({ "key": key, "message": "stored" })),
+    )
+        .into_response()
+}
+
+async fn delete_key(
+    State(shared): State<AppState>,
+    Path((collection, key)): Path<(String, String)>,
+) -> Response {
+    match shared.lock().delete(&shared.events, &collection, &key) {
+        true => Json(json!({ "key": key, "message": "deleted" })).into_response(),
+        false => not_found("key"),
+    }
+}
+
+// batches
+
+async fn batch_put(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    body: Bytes,
+) -> Response {
+    let Ok(items) = serde_json::from_slice::<Vec<BatchOperation<Value>>>(&body) else {
+        return error(StatusCode::BAD_REQUEST, "expected an array of {key, value}");
+    };
+    let mut state = shared.lock();
+    let mut succeeded = Vec::new();
+    for item in items {
+        state.put(&shared.events, &collection, &item.key, item.value, None);
+        succeeded.push(item.key);
+    }
+    Json(json!({ "succeeded": succeeded, "failed": [] })).into_response()
+}
+
+#[derive(Deserialize)]
+struct KeysBody {
+    keys: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum MockOp {
+    Put { key: String, value: Value },
+    Delete { key: String },
+    PutIfAbsent { key: String, value: Value },
+}
+
+#[derive(Deserialize)]
+struct OpsBody {
+    ops: Vec<MockOp>,
+}
+
+async fn batch(
+    State(shared): State<AppState>,
+    Path((collection, op)): Path<(String, String)>,
+    body: Bytes,
+) -> Response {
+    let mut state = shared.lock();
+    let events = &shared.events;
+    match op.as_str() {
+        "get" => {
+            let Ok(body) = serde_json::from_slice::<KeysBody>(&body) else {
+                return error(StatusCode::BAD_REQUEST, "expected {\"keys\": [...]}");
+            };
+            let found: Map<String, Value> = body
+                .keys
+                .into_iter()
+                .filter_map(|key| {
+                    let value = state.entry(&collection, &key)?.value.clone();
+                    Some((key, value))
+                })
+                .collect();
+            Json(found).into_response()
+        }
+        "delete" => {
+            let Ok(body) = serde_json::from_slice::<KeysBody>(&body) else {
+                return error(StatusCode::BAD_REQUEST, "expected {\"keys\": [...]}");
+            };
+            for key in &body.keys {
+                state.delete(events, &collection, key);
+            }
+            Json(json!({ "succeeded": body.keys, "failed": [] })).into_response()
+        }
+        "ops" => {
+            let Ok(body) = serde_json::from_slice::<OpsBody>(&body) else {
+                return error(StatusCode::BAD_REQUEST, "expected {\"ops\": [...]}");
+            };
+            let mut succeeded = Vec::new();
+            let mut failed = Vec::new();
+            for op in body.ops {
+                match op {
+                    MockOp::Put { key, value } => {
+                        state.put(events, &collection, &key, value, None);
+                        succeeded.push(key);
+                    }
+                    MockOp::Delete { key } => {
+                        state.delete(events, &collection, &key);
+                        succeeded.push(key);
+                    }
+                    MockOp::PutIfAbsent { key, value } => {
+                        if state.entry(&collection, &key).is_some() {
+                            failed.push(json!({ "key": key, "reason": "key already exists" }));
+                        } else {
+                            state.put(events, &collection, &key, value, None);
+                            succeeded.push(key);
+                        }
+                    }
+                }
+            }
+            Json(json!({ "succeeded": succeeded, "failed": failed })).into_response()
+        }
+        _ => not_found("batch operation"),
+    }
+}
+
+// subscriptions
+
+async fn subscribe(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    req: Request,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let path = req.uri().path().to_string();
+    // Event ids double as server times, so both resume from the same place.
+    let last_id = req
+        .headers()
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| params.get("since").map(String::as_str))
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    // Subscribe before reading the backlog so no event falls in between.
+    let (backlog, live, limit) = {
+        let mut state = shared.lock();
+        let live = shared.events.subscribe();
+        let backlog: Vec<_> = state
+            .collections
+            .get(&collection)
+            .map(|c| {
+                c.events
+                    .iter()
+                    .filter(|(id, _)| *id > last_id)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let limit = take_matching(&mut state.faults, "GET", &path, |kind| match kind {
+            FaultKind::DropStream { after } => Some(*after),
+            _ => None,
+        })
+        .into_iter()
+        .min();
+        (backlog, live, limit)
+    };
+
+    let replayed = backlog.last().map_or(last_id, |(id, _)| *id);
+    let live = stream::unfold(live, move |mut live| {
+        let collection = collection.clone();
+        async move {
+            loop {
+                match live.recv().await {
+                    Ok((c, id, data)) if c == collection && id > replayed => {
+                        return Some(((id, data), live))
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+
+    let events = stream::iter(backlog)
+        .chain(live)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|(id, data)| Ok(Event::default().id(id.to_string()).data(data)));
+    let hint = stream::once(async { Ok(Event::default().retry(RECONNECT_HINT)) });
+    Sse::new(hint.chain(events))
+}
+
+// import
+
+/// Content of the first part of a `multipart/form-data` body.
+fn multipart_file(headers: &HeaderMap, body: &[u8]) -> Option<Vec<u8>> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    let boundary = content_type
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{boundary}");
+
+    let find = |haystack: &[u8], needle: &[u8]| {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    };
+    let start = find(body, delimiter.as_bytes())? + delimiter.len();
+    let headers_end = start + find(&body[start..], b"\r\n\r\n")? + 4;
+    let end = headers_end + find(&body[headers_end..], format!("\r\n{delimiter}").as_bytes())?;
+    Some(body[headers_end..end].to_vec())
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |value, field| value.get(field))
+}
+
+async fn import(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(file) = multipart_file(&headers, &body) else {
+        return error(StatusCode::BAD_REQUEST, "expected a multipart file upload");
+    };
+    let Ok(values) = serde_json::from_slice::<Vec<Value>>(&file) else {
+        return error(StatusCode::BAD_REQUEST, "file must contain a JSON array");
+    };
+
+    let mut state = shared.lock();
+    let mut imported = 0;
+    let mut failures = Vec::new();
+    for value in values {
+        let key = match params.get("key") {
+            Some(path) => match lookup(&value, path) {
+                Some(Value::String(key)) => key.clone(),
+                Some(Value::Number(n)) => n.to_string(),
+                _ => {
+                    failures
+                        .push(json!({ "key": null, "reason": format!("missing key '{path}'") }));
+                    continue;
+                }
+            },
+            None => state.next_id().to_string(),
+        };
+        state.put(&shared.events, &collection, &key, value, None);
+        imported += 1;
+    }
+    Json(json!({
+        "imported": imported,
+        "failed": failures.len(),
+        "failures": failures,
+    }))
+    .into_response()
+}
+
+// backup and restore
+
+fn backup_job(id: &str, size: usize) -> Value {
+    let now = unix_now();
+    json!({
+        "id": id,
+        "status": "completed",
+        "started_at": now,
+        "finished_at": now,
+        "size": size,
+    })
+}
+
+fn store_backup(state: &mut MockState, collection: &str, data: Bytes) -> Value {
+    let id = state.next_id().to_string();
+    let job = backup_job(&id, data.len());
+    state.backups.insert(format!("{collection}-{id}.sst"), data);
+    state
+        .backup_jobs
+        .insert((collection.to_string(), id), job.clone());
+    job
+}
+
+async fn start_backup(State(shared): State<AppState>, Path(collection): Path<String>) -> Response {
+    let mut state = shared.lock();
+    let Some(c) = state.collection(&collection) else {
+        return not_found("collection");
+    };
+    let snapshot: Map<String, Value> = c
+        .entries
+        .iter()
+        .map(|(key, entry)| (key.clone(), entry.value.clone()))
+        .collect();
+    let data = Bytes::from(Value::Object(snapshot).to_string());
+    Json(store_backup(&mut state, &collection, data)).into_response()
+}
+
+async fn backup_status(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let id = params.get("id").cloned().unwrap_or_default();
+    match shared.lock().backup_jobs.get(&(collection, id)) {
+        Some(job) => Json(job.clone()).into_response(),
+        None => not_found("backup"),
+    }
+}
+
+async fn upload_backup(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(file) = multipart_file(&headers, &body) else {
+        return error(StatusCode::BAD_REQUEST, "expected a multipart file upload");
+    };
+    let expected = headers
+        .get(crate::checksum::CHECKSUM_HEADER)
+        .and_then(|v| v.to_str().ok());
+    if let Some(expected) = expected {
+        if !Checksum::of(&file).to_hex().eq_ignore_ascii_case(expected) {
+            return error(StatusCode::BAD_REQUEST, "checksum mismatch");
+        }
+    }
+    let mut state = shared.lock();
+    Json(store_backup(&mut state, &collection, file.into())).into_response()
+}
+
+async fn start_restore(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let backup_id = params.get("backup_id").cloned().unwrap_or_default();
+    let mut state = shared.lock();
+    let Some(data) = state
+        .backups
+        .get(&format!("{collection}-{backup_id}.sst"))
+        .cloned()
+    else {
+        return not_found("backup");
+    };
+
+    let id = format!("restore-{}", state.next_id());
+    let mut job = json!({ "id": id, "backup_id": backup_id, "status": "completed" });
+    match serde_json::from_slice::<Map<String, Value>>(&data) {
+        Ok(entries) => {
+            let restored = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let entry = Entry {
+                        value,
+                        version: state.next_id(),
+                        expires_at: None,
+                    };
+                    (key, entry)
+                })
+                .collect();
+            state
+                .collections
+                .entry(collection.clone())
+                .or_default()
+                .entries = restored;
+        }
+        Err(e) => {
+            job["status"] = json!("failed");
+            job["error"] = json!(format!("backup is not readable: {e}"));
+        }
+    }
+    state.restore_jobs.insert((collection, id), job.clone());
+    Json(job).into_response()
+}
+
+async fn restore_status(
+    State(shared): State<AppState>,
+    Path(collection): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let id = params.get("id").cloned().unwrap_or_default();
+    match shared.lock().restore_jobs.get(&(collection, id)) {
+        Some(job) => Json(job.clone()).into_response(),
+        None => not_found("restore"),
+    }
+}
+
+async fn backup_file(State(shared): State<AppState>, Path(file): Path<String>) -> Response {
+    let state = shared.lock();
+    if let Some(name) = file.strip_suffix(".sha256") {
+        return match state.backups.get(name) {
+            Some(data) => format!("{}  {name}\n", Checksum::of(data).to_hex()).into_response(),
+            None => not_found("backup"),
+        };
+    }
+    match state.backups.get(&file) {
+        Some(data) => (
+            [
+                (CONTENT_TYPE, "application/octet-stream".to_string()),
+                (
+                    crate::checksum::CHECKSUM_HEADER
+                        .parse()
+                        .expect("valid header name"),
+                    Checksum::of(data).to_hex(),
+                ),
+            ],
+            data.clone(),
+        )
+            .into_response(),
+        None => not_found("backup"),
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{Error, Result, Target};
+use reqwest::header::HeaderValue;
+use reqwest::{Request, Response};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::field::{display, Empty};
+use tracing::{Instrument, Span};
+
+/// W3C trace context header sent with every request.
+pub(crate) const TRACEPARENT: &str = "traceparent";
+
+/// Returns the caller's current `traceparent`, set with
+/// [`SmolKvBuilder::trace_context`](crate::SmolKvBuilder::trace_context).
+pub(crate) type TraceContext = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+fn random_hex(bytes: usize) -> String {
+    let random: Vec<u8> = (0..bytes).map(|_| fastrand::u8(..)).collect();
+    hex::encode(random)
+}
+
+/// The key as recorded in spans: as is, or the start of its SHA-256 when redacted.
+fn key_field(key: &str, redact: bool) -> String {
+    match redact {
+        true => hex::encode(&Sha256::digest(key.as_bytes())[..4]),
+        false => key.to_string(),
+    }
+}
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len
+        && s.bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Trace id, parent span id and flags of a valid version 00 `traceparent`.
+fn parse_traceparent(header: &str) -> Option<(String, String, String)> {
+    let mut parts = header.trim().split('-');
+    let (version, trace_id, parent_id, flags) =
+        (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let valid = version == "00"
+        && parts.next().is_none()
+        && is_hex(trace_id, 32)
+        && trace_id.bytes().any(|b| b != b'0')
+        && is_hex(parent_id, 16)
+        && parent_id.bytes().any(|b| b != b'0')
+        && is_hex(flags, 2);
+    valid.then(|| {
+        (
+            trace_id.to_string(),
+            parent_id.to_string(),
+            flags.to_string(),
+        )
+    })
+}
+
+/// The `smolkv.request` span covering one request and its retries.
+///
+/// Its W3C span id is recorded as `span_id` and sent as the parent of every attempt,
+/// so server spans hang off this span; `parent_span_id` links it to the caller's.
+pub(crate) struct RequestTrace {
+    span: Span,
+    trace_id: String,
+    span_id: String,
+    flags: String,
+}
+
+impl RequestTrace {
+    /// Joins the trace `context` reports, if any, or starts a new one.
+    pub fn new(target: Target<'_>, redact_keys: bool, context: Option<&TraceContext>) -> Self {
+        let (trace_id, parent_id, flags) = match context
+            .and_then(|context| context())
+            .and_then(|header| parse_traceparent(&header))
+        {
+            Some((trace_id, parent_id, flags)) => (trace_id, Some(parent_id), flags),
+            None => (random_hex(16), None, "01".to_string()),
+        };
+        let span_id = random_hex(8);
+        let key = target.key.map(|key| key_field(key, redact_keys));
+        let span = tracing::info_span!(
+            "smolkv.request",
+            collection = target.collection,
+            key = key.as_deref(),
+            method = Empty,
+            path = Empty,
+            status = Empty,
+            retries = 0,
+            response_size = Empty,
+            latency_ms = Empty,
+            error = Empty,
+            trace_id = %trace_id,
+            span_id = %span_id,
+            parent_span_id = parent_id.as_deref(),
+        );
+        Self {
+            span,
+            trace_id,
+            span_id,
+            flags,
+        }
+    }
+
+    /// Records the method and path of an attempt and, unless the client already sends
+    /// one, adds a `traceparent` header naming this span as the parent.
+    pub fn start_attempt(&self, mut request: Request, propagate: bool) -> Request {
+        if propagate {
+            let parent = format!("00-{}-{}-{}", self.trace_id, self.span_id, self.flags);
+            let parent = HeaderValue::from_str(&parent).expect("traceparent is ascii");
+            request.headers_mut().insert(TRACEPARENT, parent);
+        }
+        self.span.record("method", request.method().as_str());
+        self.span.record("path", request.url().path());
+        request
+    }
+
+    pub fn retrying(&self, attempt: u32, err: &Error) {
+        self.span.record("retries", attempt);
+        tracing::debug!(parent: &self.span, attempt, error = %err, "retrying request");
+    }
+
+    pub async fn run(&self, attempts: impl Future<Output = Result<Response>>) -> Result<Response> {
+        let started = Instant::now();
+        let result = attempts.instrument(self.span.clone()).await;
+        self.span
+            .record("latency_ms", started.elapsed().as_millis() as u64);
+        match &result {
+            Ok(resp) => {
+                self.span.record("status", resp.status().as_u16());
+                if let Some(size) = resp.content_length() {
+                    self.span.record("response_size", size);
+                }
+            }
+            Err(e) => {
+                if let Some(status) = e.context().and_then(|c| c.status) {
+                    self.span.record("status", status.as_u16());
+                }
+                self.span.record("error", display(e));
+            }
+        }
+        result
+    }
+}
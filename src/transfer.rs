@@ -0,0 +1,341 @@
+use crate::checksum::{manifest_path, write_manifest, Checksum, CHECKSUM_HEADER};
+use crate::{BackupJob, ImportReport, Result, SmolKv, Target};
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use reqwest::{Method, Url};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+/// Stream of raw bytes, e.g. a backup file being downloaded.
+pub type ByteStream = BoxStream<'static, Result<Bytes>>;
+
+/// Number of bytes moved so far, passed to progress callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub transferred: u64,
+    /// Total size, when known up front.
+    pub total: Option<u64>,
+}
+
+/// Outcome of downloading a backup to a writer or file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadReport {
+    pub size: u64,
+    pub checksum: Checksum,
+    /// Whether the server provided a checksum and it matched.
+    pub verified: bool,
+}
+
+impl SmolKv {
+    /// Opens a backup download together with the checksum the server advertises for it,
+    /// taken from the response header or, failing that, a `.sha256` sidecar file.
+    pub(crate) async fn open_backup_download(
+        &self,
+        operation: &'static str,
+        collection: &str,
+        backup_id: &str,
+    ) -> Result<(reqwest::Response, Option<Checksum>)> {
+        let url = self.backup_file_url(collection, backup_id)?;
+        let req = self.request(Method::GET, url.clone());
+        let resp = self
+            .send(req, Target::collection(operation, collection))
+            .await?;
+
+        let header = resp
+            .headers()
+            .get(CHECKSUM_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        let expected = match header {
+            Some(checksum) => Some(checksum),
+            None => self.fetch_sidecar_checksum(collection, url).await,
+        };
+        Ok((resp, expected))
+    }
+
+    /// Reads the checksum from the backup's `.sha256` sidecar file. The sidecar is optional,
+    /// so one that is missing, fails to load or does not parse counts as no checksum.
+    async fn fetch_sidecar_checksum(&self, collection: &str, url: Url) -> Option<Checksum> {
+        let mut sidecar = url;
+        sidecar.set_path(&format!("{}.sha256", sidecar.path()));
+
+        let req = self.request(Method::GET, sidecar);
+        let target = Target::collection("backup_checksum", collection);
+        let resp = self.send(req, target).await.ok()?;
+        let text = resp.text().await.ok()?;
+        text.split_whitespace().next()?.parse().ok()
+    }
+
+    /// Downloads a backup as a stream of chunks instead of buffering the whole file.
+    ///
+    /// When the server provides a checksum, the stream ends with
+    /// [`Error::ChecksumMismatch`](crate::Error::ChecksumMismatch) if the received bytes
+    /// do not match it.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.download_backup_stream", skip_all)
+    )]
+    pub async fn download_backup_stream(
+        &self,
+        collection: &str,
+        backup_id: &str,
+        progress: impl Fn(TransferProgress) + Send + 'static,
+    ) -> Result<ByteStream> {
+        let (resp, expected) = self
+            .open_backup_download("download_backup_stream", collection, backup_id)
+            .await?;
+
+        let total = resp.content_length();
+        let mut transferred = 0;
+        let chunks = resp.bytes_stream().map(move |chunk| {
+            let chunk = chunk?;
+            transferred += chunk.len() as u64;
+            progress(TransferProgress { transferred, total });
+            Ok(chunk)
+        });
+
+        let state = (chunks.boxed(), Some(Sha256::new()), expected);
+        Ok(
+            stream::unfold(state, |(mut chunks, mut hasher, expected)| async move {
+                let digest = hasher.as_mut()?;
+                match chunks.next().await {
+                    Some(Ok(chunk)) => {
+                        digest.update(&chunk);
+                        Some((Ok(chunk), (chunks, hasher, expected)))
+                    }
+                    Some(Err(e)) => Some((Err(e), (chunks, None, expected))),
+                    None => {
+                        let actual = Checksum::from_hasher(hasher.take()?);
+                        match expected.map(|expected| actual.verify(&expected)) {
+                            Some(Err(e)) => Some((Err(e), (chunks, None, expected))),
+                            _ => None,
+                        }
+                    }
+                }
+            })
+            .boxed(),
+        )
+    }
+
+    /// Downloads a backup into `writer`, hashing it on the way and checking it against
+    /// the server's checksum when there is one.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.download_backup_to", skip_all)
+    )]
+    pub async fn download_backup_to<W: AsyncWrite + Unpin>(
+        &self,
+        collection: &str,
+        backup_id: &str,
+        writer: &mut W,
+        progress: impl Fn(TransferProgress) + Send + 'static,
+    ) -> Result<DownloadReport> {
+        self.download_into(
+            "download_backup_to",
+            collection,
+            backup_id,
+            writer,
+            progress,
+        )
+        .await
+    }
+
+    async fn download_into<W: AsyncWrite + Unpin>(
+        &self,
+        operation: &'static str,
+        collection: &str,
+        backup_id: &str,
+        writer: &mut W,
+        progress: impl Fn(TransferProgress) + Send + 'static,
+    ) -> Result<DownloadReport> {
+        let (resp, expected) = self
+            .open_backup_download(operation, collection, backup_id)
+            .await?;
+        let mut digest = DownloadDigest::new(resp.content_length(), expected);
+        let mut chunks = resp.bytes_stream();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            writer.write_all(&chunk).await?;
+            progress(digest.update(&chunk));
+        }
+        writer.flush().await?;
+        digest.finish()
+    }
+
+    /// Downloads a backup to `path` and writes a `<path>.sha256` manifest next to it,
+    /// which [`verify_backup_file`](crate::checksum::verify_backup_file) checks later.
+    ///
+    /// The data goes to a temporary `.part` file first, which only replaces `path` once
+    /// the checksum matched, so a corrupt download never ends up under the final name.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.download_backup_to_file", skip_all)
+    )]
+    pub async fn download_backup_to_file(
+        &self,
+        collection: &str,
+        backup_id: &str,
+        path: impl AsRef<Path>,
+        progress: impl Fn(TransferProgress) + Send + 'static,
+    ) -> Result<DownloadReport> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+
+        let mut file = tokio::fs::File::create(&partial).await?;
+        let result = self
+            .download_into(
+                "download_backup_to_file",
+                collection,
+                backup_id,
+                &mut file,
+                progress,
+            )
+            .await;
+        drop(file);
+        let report = match result {
+            Ok(report) => report,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                return Err(e);
+            }
+        };
+
+        tokio::fs::rename(&partial, path).await?;
+        write_manifest(manifest_path(path), path, &report.checksum).await?;
+        Ok(report)
+    }
+
+    /// Uploads a backup of `len` bytes read from `reader` as a streamed multipart body.
+    ///
+    /// Pass the file's checksum, e.g. from [`Checksum::of_reader`], to let the server
+    /// reject corrupted uploads. The body cannot be replayed, so the upload is never
+    /// retried.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.upload_backup_from", skip_all)
+    )]
+    pub async fn upload_backup_from<R: AsyncRead + Send + Sync + 'static>(
+        &self,
+        collection: &str,
+        reader: R,
+        len: u64,
+        checksum: Option<Checksum>,
+        progress: impl Fn(TransferProgress) + Send + Sync + 'static,
+    ) -> Result<BackupJob> {
+        let part =
+            streamed_part(reader, len, progress).file_name(format!("{collection}-backup.sst"));
+        let form = reqwest::multipart::Form::new().part("file", part);
+
+        let mut req = self
+            .request(Method::POST, self.url(&[collection, "_backup", "upload"])?)
+            .multipart(form);
+        if let Some(checksum) = checksum {
+            req = req.header(CHECKSUM_HEADER, checksum.to_hex());
+        }
+        let resp = self
+            .send(
+                req,
+                Target::collection("upload_backup_from", collection).non_idempotent(),
+            )
+            .await?;
+        Self::handle_response(resp).await
+    }
+
+    /// Imports values from a JSON array of `len` bytes read from `reader`, streamed
+    /// like [`SmolKv::upload_backup_from`] instead of held in memory. The body cannot
+    /// be replayed, so the import is never retried.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "smolkv.import_values_from", skip_all)
+    )]
+    pub async fn import_values_from<R: AsyncRead + Send + Sync + 'static>(
+        &self,
+        collection: &str,
+        key: Option<String>,
+        reader: R,
+        len: u64,
+        progress: impl Fn(TransferProgress) + Send + Sync + 'static,
+    ) -> Result<ImportReport> {
+        let part = streamed_part(reader, len, progress).file_name("backup.sst");
+        let form = reqwest::multipart::Form::new().part("file", part);
+
+        let req = self
+            .request(Method::POST, self.url(&[collection, "_import"])?)
+            .multipart(form)
+            .query(&[("key", &key)]);
+        let resp = self
+            .send(
+                req,
+                Target::collection("import_values_from", collection).non_idempotent(),
+            )
+            .await?;
+        Self::handle_response(resp).await
+    }
+}
+
+/// Size and hash of a backup download so far, checked against the server's checksum
+/// once the last chunk is in.
+pub(crate) struct DownloadDigest {
+    hasher: Sha256,
+    size: u64,
+    total: Option<u64>,
+    expected: Option<Checksum>,
+}
+
+impl DownloadDigest {
+    pub(crate) fn new(total: Option<u64>, expected: Option<Checksum>) -> Self {
+        Self {
+            hasher: Sha256::new(),
+            size: 0,
+            total,
+            expected,
+        }
+    }
+
+    /// Adds a received chunk and returns the progress to report for it.
+    pub(crate) fn update(&mut self, chunk: &[u8]) -> TransferProgress {
+        self.hasher.update(chunk);
+        self.size += chunk.len() as u64;
+        TransferProgress {
+            transferred: self.size,
+            total: self.total,
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<DownloadReport> {
+        let checksum = Checksum::from_hasher(self.hasher);
+        if let Some(expected) = &self.expected {
+            checksum.verify(expected)?;
+        }
+        Ok(DownloadReport {
+            size: self.size,
+            checksum,
+            verified: self.expected.is_some(),
+        })
+    }
+}
+
+/// A multipart file part streaming `len` bytes from `reader`, reporting progress as
+/// they are sent.
+fn streamed_part<R: AsyncRead + Send + Sync + 'static>(
+    reader: R,
+    len: u64,
+    progress: impl Fn(TransferProgress) + Send + Sync + 'static,
+) -> reqwest::multipart::Part {
+    let mut transferred = 0;
+    let stream = ReaderStream::new(reader).map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            transferred += chunk.len() as u64;
+            progress(TransferProgress {
+                transferred,
+                total: Some(len),
+            });
+        }
+        chunk
+    });
+    reqwest::multipart::Part::stream_with_length(reqwest::Body::wrap_stream(stream), len)
+}
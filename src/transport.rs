@@ -0,0 +1,189 @@
+use crate::{Error, ErrorContext, Result};
+use futures_util::future::BoxFuture;
+use reqwest::{Client, Request, Response};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+/// Sends HTTP requests for a [`SmolKv`](crate::SmolKv) client.
+///
+/// Every request the client makes goes through its transport, which is a
+/// `reqwest::Client` by default. Implement it to plug in test doubles, or wrap an
+/// existing transport with a [`Layer`] to add behavior such as auth refresh, logging
+/// or rate limiting. Non-success statuses should be returned as responses, not errors,
+/// so the client can map them to typed errors and apply its
+/// [`RetryPolicy`](crate::RetryPolicy).
+///
+/// Responses can be built from an `http::Response` with `reqwest::Response::from`.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>>;
+}
+
+impl Transport for Client {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move { Ok(self.execute(request).await?) })
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        (**self).send(request)
+    }
+}
+
+/// A transport shared between clones of a client.
+pub type SharedTransport = Arc<dyn Transport>;
+
+/// Wraps a transport in another one, like a tower layer.
+///
+/// Layers are added with [`SmolKvBuilder::layer`](crate::SmolKvBuilder::layer); the
+/// first one added is the outermost and sees each request first. Closures taking and
+/// returning a [`SharedTransport`] are layers too.
+pub trait Layer: Send + Sync {
+    fn layer(&self, inner: SharedTransport) -> SharedTransport;
+}
+
+impl<F> Layer for F
+where
+    F: Fn(SharedTransport) -> SharedTransport + Send + Sync,
+{
+    fn layer(&self, inner: SharedTransport) -> SharedTransport {
+        self(inner)
+    }
+}
+
+fn context(request: &Request) -> ErrorContext {
+    ErrorContext {
+        method: request.method().clone(),
+        path: request.url().path().to_string(),
+        status: None,
+        collection: None,
+        key: None,
+        body: None,
+    }
+}
+
+/// Fails requests that take longer than a fixed time with [`Error::Timeout`],
+/// including time spent waiting in inner layers.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeoutLayer {
+    timeout: Duration,
+}
+
+impl TimeoutLayer {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl Layer for TimeoutLayer {
+    fn layer(&self, inner: SharedTransport) -> SharedTransport {
+        Arc::new(Timeout {
+            inner,
+            timeout: self.timeout,
+        })
+    }
+}
+
+struct Timeout {
+    inner: SharedTransport,
+    timeout: Duration,
+}
+
+impl Transport for Timeout {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let context = context(&request);
+            tokio::time::timeout(self.timeout, self.inner.send(request))
+                .await
+                .map_err(|_| Error::Timeout(Box::new(context)))?
+        })
+    }
+}
+
+/// Limits how many requests are in flight at once, across clones of the client.
+#[derive(Debug, Clone, Copy)]
+pub struct ConcurrencyLimitLayer {
+    limit: usize,
+}
+
+impl ConcurrencyLimitLayer {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+        }
+    }
+}
+
+impl Layer for ConcurrencyLimitLayer {
+    fn layer(&self, inner: SharedTransport) -> SharedTransport {
+        Arc::new(ConcurrencyLimit {
+            inner,
+            permits: Semaphore::new(self.limit),
+        })
+    }
+}
+
+struct ConcurrencyLimit {
+    inner: SharedTransport,
+    permits: Semaphore,
+}
+
+impl Transport for ConcurrencyLimit {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            // The semaphore is never closed.
+            let _permit = self.permits.acquire().await.expect("semaphore closed");
+            self.inner.send(request).await
+        })
+    }
+}
+
+/// Spaces requests out so that at most `requests` start in any `period`.
+///
+/// Streaming responses such as subscriptions count once, when they are opened.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitLayer {
+    interval: Duration,
+}
+
+impl RateLimitLayer {
+    pub fn new(requests: u32, period: Duration) -> Self {
+        Self {
+            interval: period / requests.max(1),
+        }
+    }
+}
+
+impl Layer for RateLimitLayer {
+    fn layer(&self, inner: SharedTransport) -> SharedTransport {
+        Arc::new(RateLimit {
+            inner,
+            interval: self.interval,
+            next_slot: Mutex::new(None),
+        })
+    }
+}
+
+struct RateLimit {
+    inner: SharedTransport,
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl Transport for RateLimit {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let slot = {
+                let mut next = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                let slot = next.map_or(now, |next| next.max(now));
+                *next = Some(slot + self.interval);
+                slot
+            };
+            tokio::time::sleep_until(slot).await;
+            self.inner.send(request).await
+        })
+    }
+}
@@ -0,0 +1,124 @@
+use serde_json::json;
+use smolkv_client::testing::{Fault, MockServer};
+use smolkv_client::{BatchOperation, RetryPolicy, SmolKv};
+use std::time::{Duration, Instant};
+
+const BATCH_PATH: &str = "/api/things/_batch";
+
+fn ops(keys: &[&str], value: &str) -> Vec<BatchOperation<String>> {
+    keys.iter()
+        .map(|key| BatchOperation {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+fn batch_requests(server: &MockServer) -> usize {
+    server
+        .requests()
+        .iter()
+        .filter(|req| req.method == "PUT" && req.path == BATCH_PATH)
+        .count()
+}
+
+#[tokio::test]
+async fn chunks_by_item_count() {
+    let server = MockServer::start().await;
+    let writer = server.client().batch_writer("things").max_items(3);
+    let keys = ["k0", "k1", "k2", "k3", "k4", "k5", "k6", "k7", "k8", "k9"];
+
+    let mut report = writer.write(ops(&keys, "v")).await.unwrap();
+    report.succeeded.sort();
+    assert_eq!(report.succeeded, keys);
+    assert!(report.is_success());
+    assert_eq!(batch_requests(&server), 4);
+    assert_eq!(server.keys("things"), keys);
+}
+
+#[tokio::test]
+async fn chunks_by_size() {
+    let server = MockServer::start().await;
+    let keys = ["k0", "k1", "k2", "k3", "k4"];
+    let value = "x".repeat(40);
+    let item = serde_json::to_vec(&ops(&keys[..1], &value)[0]).unwrap();
+    // Room for exactly two items, their separator and the brackets.
+    let writer = server
+        .client()
+        .batch_writer("things")
+        .max_bytes(2 * item.len() + 3);
+
+    let report = writer.write(ops(&keys, &value)).await.unwrap();
+    assert!(report.is_success());
+    assert_eq!(batch_requests(&server), 3);
+    assert_eq!(server.keys("things"), keys);
+}
+
+#[tokio::test]
+async fn splits_chunks_the_server_finds_too_large() {
+    let server = MockServer::start().await;
+    server.inject(Fault::status(413).path(BATCH_PATH).times(1));
+    let keys = ["a", "b", "c", "d"];
+
+    let report = server
+        .client()
+        .batch_writer("things")
+        .write(ops(&keys, "v"))
+        .await
+        .unwrap();
+    assert!(report.is_success());
+    assert_eq!(batch_requests(&server), 3);
+    assert_eq!(server.keys("things"), keys);
+}
+
+#[tokio::test]
+async fn reports_failures_per_key() {
+    let server = MockServer::start().await;
+    server.inject(Fault::status(400).path(BATCH_PATH).times(1));
+    let mut items = ops(&["a", "b"], "v");
+    items.extend(ops(&["huge"], &"x".repeat(200)));
+    items.extend(ops(&["c", "d"], "v"));
+
+    let report = server
+        .client()
+        .batch_writer("things")
+        .max_items(2)
+        .max_bytes(100)
+        .parallelism(1)
+        .write(items)
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded, ["c", "d"]);
+    let mut failed: Vec<_> = report.failed.iter().map(|f| f.key.as_str()).collect();
+    failed.sort();
+    assert_eq!(failed, ["a", "b", "huge"]);
+
+    let huge = report.failed.iter().find(|f| f.key == "huge").unwrap();
+    assert!(
+        huge.reason.contains("100 byte batch limit"),
+        "{}",
+        huge.reason
+    );
+    assert!(report.failed.iter().all(|f| !f.retryable));
+    assert_eq!(server.keys("things"), ["c", "d"]);
+}
+
+#[tokio::test]
+async fn chunk_retries_wait_for_retry_after() {
+    let server = MockServer::start().await;
+    server.inject(Fault::status(503).retry_after(1).path(BATCH_PATH).times(1));
+    let kv = SmolKv::builder(server.url())
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    let report = kv
+        .batch_writer("things")
+        .write(ops(&["a"], "v"))
+        .await
+        .unwrap();
+    assert!(report.is_success());
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.value("things", "a"), Some(json!("v")));
+}
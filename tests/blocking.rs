@@ -0,0 +1,161 @@
+#![cfg(feature = "blocking")]
+
+use serde_json::{json, Value};
+use smolkv_client::blocking::SmolKv;
+use smolkv_client::testing::MockServer;
+use smolkv_client::{Checksum, Error, QueryBuilder};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// The mock server runs on its own runtime, since the blocking client must not be used
+/// from within one.
+fn start() -> (Runtime, MockServer, SmolKv) {
+    let runtime = Runtime::new().unwrap();
+    let server = runtime.block_on(MockServer::start());
+    let kv = SmolKv::new(server.url(), None::<String>).unwrap();
+    (runtime, server, kv)
+}
+
+#[test]
+fn key_operations() {
+    let (_runtime, server, kv) = start();
+
+    kv.put("users", "bob", &json!({ "age": 42 })).unwrap();
+    let value: Value = kv.get("users", "bob").unwrap();
+    assert_eq!(value, json!({ "age": 42 }));
+    assert!(kv.exists("users", "bob").unwrap());
+    assert!(matches!(
+        kv.get::<Value>("users", "alice"),
+        Err(Error::NotFound(_))
+    ));
+
+    assert!(kv.delete("users", "bob").unwrap());
+    assert!(server.keys("users").is_empty());
+}
+
+#[test]
+fn query_iterator() {
+    let (_runtime, server, kv) = start();
+    for (i, key) in ["a", "b", "c"].iter().enumerate() {
+        server.insert("letters", key, json!(i));
+    }
+
+    let items: Vec<(String, i32)> = kv
+        .query_iter("letters", QueryBuilder::new(), 2)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        items,
+        [
+            ("a".to_string(), 0),
+            ("b".to_string(), 1),
+            ("c".to_string(), 2)
+        ]
+    );
+}
+
+#[test]
+fn subscribe_iterator() {
+    let (runtime, server, kv) = start();
+    let mut events = kv.subscribe("users");
+
+    let writer = server.client();
+    runtime.spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        writer.put("users", "bob", &1).await.unwrap();
+        writer.delete("users", "bob").await.unwrap();
+    });
+
+    let put = events.next().unwrap().unwrap();
+    assert_eq!((put.operation.as_str(), put.key.as_str()), ("put", "bob"));
+    assert_eq!(put.value, json!(1));
+    let delete = events.next().unwrap().unwrap();
+    assert_eq!(
+        (delete.operation.as_str(), delete.key.as_str()),
+        ("delete", "bob")
+    );
+}
+
+#[test]
+fn typed_collections() {
+    let (_runtime, server, kv) = start();
+    let ages = kv.collection::<u32>("ages");
+
+    ages.put("bob", &42).unwrap();
+    assert_eq!(ages.get("bob").unwrap(), 42);
+    assert_eq!(ages.update("bob", |age| age.unwrap_or(0) + 1).unwrap(), 43);
+    assert_eq!(ages.list(QueryBuilder::new()).unwrap(), [43]);
+    assert!(ages.delete("bob").unwrap());
+    assert!(server.keys("ages").is_empty());
+}
+
+#[test]
+fn batch_writers() {
+    let (_runtime, server, kv) = start();
+    let items = (0..5).map(|i| smolkv_client::BatchOperation {
+        key: format!("k{i}"),
+        value: i,
+    });
+
+    let report = kv
+        .batch_writer("numbers")
+        .max_items(2)
+        .write(items)
+        .unwrap();
+    assert!(report.is_success());
+    assert_eq!(server.keys("numbers").len(), 5);
+}
+
+#[test]
+fn spawned_sweepers_run_between_calls() {
+    let (_runtime, server, kv) = start();
+    server.insert("sessions", "old", json!({ "value": 1, "expires_at": 1 }));
+    let sweeper = kv
+        .expiry_sweeper("sessions")
+        .interval(Duration::from_millis(20));
+    sweeper
+        .put_with_ttl("fresh", &2, Duration::from_secs(60))
+        .unwrap();
+    assert_eq!(sweeper.get::<i32>("fresh").unwrap(), Some(2));
+
+    let handle = sweeper.spawn();
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(server.keys("sessions"), ["fresh"]);
+    // The client keeps working while the sweeper thread drives the runtime.
+    assert!(kv.exists("sessions", "fresh").unwrap());
+    drop(handle);
+}
+
+#[test]
+fn backups_stream_out_and_back_in() {
+    let (_runtime, server, kv) = start();
+    server.insert("users", "bob", json!({ "age": 42 }));
+    let id = kv.start_backup("users").unwrap().id.unwrap();
+
+    let mut data = Vec::new();
+    for chunk in kv.download_backup_iter("users", &id, |_| {}).unwrap() {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    let mut copy = Vec::new();
+    let report = kv
+        .download_backup_to("users", &id, &mut copy, |_| {})
+        .unwrap();
+    assert_eq!(copy, data);
+    assert!(report.verified);
+
+    let checksum = Checksum::of(&data);
+    let uploaded = kv
+        .upload_backup_from(
+            "users",
+            std::io::Cursor::new(data.clone()),
+            data.len() as u64,
+            Some(checksum),
+            |_| {},
+        )
+        .unwrap();
+    let uploaded_id = uploaded.id.unwrap();
+    assert_eq!(
+        kv.download_backup("users", &uploaded_id).unwrap(),
+        data.as_slice()
+    );
+}
@@ -0,0 +1,67 @@
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+use smolkv_client::{Error, SmolKv, Transport};
+use std::sync::{Arc, Mutex};
+
+/// Answers every request with `null`, remembering the URLs asked for.
+#[derive(Default)]
+struct Urls(Mutex<Vec<String>>);
+
+impl Transport for Urls {
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        self.0.lock().unwrap().push(request.url().to_string());
+        Box::pin(async { Ok(http::Response::new("null").into()) })
+    }
+}
+
+#[test]
+fn invalid_secrets_are_config_errors() {
+    let result = SmolKv::builder("http://localhost:5050")
+        .secret(Some("line\nbreak"))
+        .build();
+    assert!(matches!(result, Err(Error::Config(_))));
+    assert!(matches!(
+        SmolKv::new("http://localhost:5050", Some("line\nbreak")),
+        Err(Error::Config(_))
+    ));
+}
+
+#[test]
+fn endpoints_must_be_http() {
+    for endpoint in [
+        "ftp://localhost:5050",
+        "file:///tmp/kv",
+        "localhost:5050",
+        "",
+    ] {
+        let result = SmolKv::builder(endpoint).build();
+        assert!(matches!(result, Err(Error::Config(_))), "{endpoint}");
+    }
+    assert!(SmolKv::builder("https://localhost:5050").build().is_ok());
+}
+
+#[tokio::test]
+async fn trailing_slashes_are_dropped() {
+    for (endpoint, expected) in [
+        (
+            "http://smolkv.invalid/",
+            "http://smolkv.invalid/api/users/bob",
+        ),
+        (
+            "http://smolkv.invalid//",
+            "http://smolkv.invalid/api/users/bob",
+        ),
+        (
+            "http://smolkv.invalid/kv/",
+            "http://smolkv.invalid/kv/api/users/bob",
+        ),
+    ] {
+        let urls = Arc::new(Urls::default());
+        let kv = SmolKv::builder(endpoint)
+            .transport(urls.clone())
+            .build()
+            .unwrap();
+        kv.get::<Value>("users", "bob").await.unwrap();
+        assert_eq!(*urls.0.lock().unwrap(), [expected], "{endpoint}");
+    }
+}
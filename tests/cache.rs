@@ -0,0 +1,178 @@
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use smolkv_client::testing::MockServer;
+use smolkv_client::{
+    BatchOperation, CacheStats, CachedKv, Error, KvStore, MemoryKv, SharedTransport, SmolKv,
+    Transport,
+};
+use std::time::Duration;
+
+/// Waits for the background subscription to catch up.
+async fn eventually(mut check: impl FnMut() -> bool) {
+    for _ in 0..200 {
+        if check() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("condition not met in time");
+}
+
+#[tokio::test]
+async fn reads_are_served_from_memory() {
+    let store = MemoryKv::new();
+    store.put("config", "mode", &json!("fast")).await.unwrap();
+    let kv = CachedKv::new(store);
+
+    for _ in 0..3 {
+        let value: String = kv.get("config", "mode").await.unwrap();
+        assert_eq!(value, "fast");
+    }
+    assert!(kv.exists("config", "mode").await.unwrap());
+    for _ in 0..2 {
+        assert!(matches!(
+            kv.get::<Value>("config", "missing").await,
+            Err(Error::NotFound(_))
+        ));
+    }
+    assert!(!kv.exists("config", "missing").await.unwrap());
+
+    let stats = kv.stats();
+    assert_eq!((stats.hits, stats.misses), (5, 2));
+}
+
+#[tokio::test]
+async fn writes_go_through() {
+    let store = MemoryKv::new();
+    let kv = CachedKv::new(store.clone());
+
+    kv.put("users", "bob", &json!({ "age": 42 })).await.unwrap();
+    let value: Value = store.get("users", "bob").await.unwrap();
+    assert_eq!(value, json!({ "age": 42 }));
+    let value: Value = kv.get("users", "bob").await.unwrap();
+    assert_eq!(value, json!({ "age": 42 }));
+
+    assert!(kv.delete("users", "bob").await.unwrap());
+    assert!(!store.exists("users", "bob").await.unwrap());
+    assert!(!kv.exists("users", "bob").await.unwrap());
+
+    let items: Vec<_> = ["a", "b"]
+        .into_iter()
+        .map(|key| BatchOperation {
+            key: key.to_string(),
+            value: json!(key),
+        })
+        .collect();
+    kv.batch_put("users", &items).await.unwrap();
+    let value: String = kv.get("users", "b").await.unwrap();
+    assert_eq!(value, "b");
+
+    assert_eq!(kv.stats().misses, 0);
+}
+
+#[tokio::test]
+async fn changes_invalidate_entries() {
+    let store = MemoryKv::new();
+    store.put("config", "mode", &json!("fast")).await.unwrap();
+    let kv = CachedKv::new(store.clone());
+    let _: Value = kv.get("config", "mode").await.unwrap();
+
+    store.put("config", "mode", &json!("safe")).await.unwrap();
+    eventually(|| kv.stats().invalidations == 1).await;
+    let value: String = kv.get("config", "mode").await.unwrap();
+    assert_eq!(value, "safe");
+
+    store.delete("config", "mode").await.unwrap();
+    eventually(|| kv.stats().invalidations == 2).await;
+    assert!(!kv.exists("config", "mode").await.unwrap());
+    assert_eq!(kv.stats().hits, 0);
+}
+
+#[tokio::test]
+async fn entries_are_bounded_and_expire() {
+    let store = MemoryKv::new();
+    for key in ["a", "b", "c"] {
+        store.put("letters", key, &json!(key)).await.unwrap();
+    }
+    let kv = CachedKv::new(store)
+        .max_entries(2)
+        .ttl(Duration::from_millis(100));
+
+    for key in ["a", "b", "a", "c", "a", "b"] {
+        let _: Value = kv.get("letters", key).await.unwrap();
+    }
+    assert_eq!(
+        kv.stats(),
+        CacheStats {
+            hits: 2,
+            misses: 4,
+            evictions: 2,
+            invalidations: 0,
+        }
+    );
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let _: Value = kv.get("letters", "b").await.unwrap();
+    assert_eq!(kv.stats().misses, 5);
+}
+
+#[tokio::test]
+async fn instances_stay_coherent() {
+    let server = MockServer::start().await;
+    server.insert("config", "mode", json!("fast"));
+    let first = CachedKv::new(server.client());
+    let second = CachedKv::new(server.client());
+
+    // Events replayed while the subscription connects keep the first reads uncached.
+    while first.stats().hits == 0 {
+        let value: String = first.get("config", "mode").await.unwrap();
+        assert_eq!(value, "fast");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    second.put("config", "mode", &json!("safe")).await.unwrap();
+
+    eventually(|| first.stats().invalidations > 0).await;
+    let value: String = first.get("config", "mode").await.unwrap();
+    assert_eq!(value, "safe");
+}
+
+/// Holds back PUT responses after the server applied them.
+struct SlowPuts(SharedTransport);
+
+impl Transport for SlowPuts {
+    fn send(&self, req: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        Box::pin(async move {
+            let slow = req.method() == reqwest::Method::PUT;
+            let resp = self.0.send(req).await?;
+            if slow {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+            }
+            Ok(resp)
+        })
+    }
+}
+
+#[tokio::test]
+async fn newer_changes_win_over_slow_writes() {
+    let server = MockServer::start().await;
+    server.insert("config", "mode", json!("fast"));
+    let slow = SmolKv::builder(server.url())
+        .layer(|inner| std::sync::Arc::new(SlowPuts(inner)) as SharedTransport)
+        .build()
+        .unwrap();
+    let first = CachedKv::new(slow);
+    while first.stats().hits == 0 {
+        let _: Value = first.get("config", "mode").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let writer = first.clone();
+    let write =
+        tokio::spawn(async move { writer.put("config", "mode", &json!("old")).await.unwrap() });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    server.insert("config", "mode", json!("new"));
+    write.await.unwrap();
+
+    let value: String = first.get("config", "mode").await.unwrap();
+    assert_eq!(value, "new");
+}
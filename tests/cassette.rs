@@ -0,0 +1,178 @@
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use smolkv_client::testing::{Cassette, MockServer, ReplayMode};
+use smolkv_client::{Error, SharedTransport, SmolKv, Transport};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Replays never reach the network, so any host works.
+const OFFLINE: &str = "http://cassette.invalid";
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("smolkv-cassette-{name}-{}.json", fastrand::u64(..)))
+}
+
+async fn exercise(kv: &SmolKv) -> (Value, bool) {
+    kv.put("users", "bob", &json!({ "age": 42 })).await.unwrap();
+    let value = kv.get("users", "bob").await.unwrap();
+    let exists = kv.exists("users", "alice").await.unwrap();
+    (value, exists)
+}
+
+async fn record(name: &str) -> PathBuf {
+    let server = MockServer::start().await;
+    let cassette = Cassette::record();
+    exercise(&cassette.client(server.url())).await;
+
+    let path = cassette_path(name);
+    cassette.save(&path).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn strict_replay_without_a_server() {
+    let path = record("strict").await;
+
+    let cassette = Cassette::replay(&path, ReplayMode::Strict).unwrap();
+    let (value, exists) = exercise(&cassette.client(OFFLINE)).await;
+    assert_eq!(value, json!({ "age": 42 }));
+    assert!(!exists);
+    cassette.assert_done();
+
+    // A different body no longer matches the recorded PUT.
+    let cassette = Cassette::replay(&path, ReplayMode::Strict).unwrap();
+    let err = cassette
+        .client(OFFLINE)
+        .put("users", "bob", &json!({ "age": 43 }))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Io(_)), "{err}");
+    assert_eq!(cassette.unmatched(), ["PUT /api/users/bob"]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn lenient_replay_ignores_order_and_bodies() {
+    let path = record("lenient").await;
+
+    let cassette = Cassette::replay(&path, ReplayMode::Lenient).unwrap();
+    let kv = cassette.client(OFFLINE);
+    assert!(!kv.exists("users", "alice").await.unwrap());
+    let value: Value = kv.get("users", "bob").await.unwrap();
+    assert_eq!(value, json!({ "age": 42 }));
+    kv.put("users", "bob", &json!({ "age": 7 })).await.unwrap();
+    cassette.assert_done();
+
+    // Every interaction is replayed once.
+    assert!(kv.get::<Value>("users", "bob").await.is_err());
+    assert_eq!(cassette.unmatched(), ["GET /api/users/bob"]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn unmatched_requests_do_not_look_like_missing_endpoints() {
+    let path = record("unmatched").await;
+
+    // Without a recorded `_batch/get`, batch_get must fail instead of quietly falling
+    // back to the recorded single-key GET.
+    let cassette = Cassette::replay(&path, ReplayMode::Lenient).unwrap();
+    let err = cassette
+        .client(OFFLINE)
+        .batch_get::<Value>("users", &["bob"])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Io(_)), "{err}");
+    assert_eq!(cassette.unmatched(), ["POST /api/users/_batch/get"]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn records_subscription_frames() {
+    let server = MockServer::start().await;
+    server.insert("users", "bob", json!(1));
+    server.insert("users", "alice", json!(2));
+
+    let cassette = Cassette::record();
+    let keys = |kv: SmolKv| async move {
+        let events = kv.subscribe_events("users");
+        events
+            .take(2)
+            .map(|event| event.unwrap().key)
+            .collect::<Vec<_>>()
+            .await
+    };
+    assert_eq!(keys(cassette.client(server.url())).await, ["bob", "alice"]);
+
+    let path = cassette_path("subscribe");
+    cassette.save(&path).unwrap();
+    drop(server);
+
+    let replay = Cassette::replay(&path, ReplayMode::Strict).unwrap();
+    assert_eq!(keys(replay.client(OFFLINE)).await, ["bob", "alice"]);
+    replay.assert_done();
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn layers_a_custom_client() {
+    let server = MockServer::start().await;
+    let cassette = Cassette::record();
+    let kv = SmolKv::builder(server.url())
+        .secret(Some("hunter2"))
+        .header("authorization", "Bearer hunter3")
+        .header("cookie", "session=hunter4")
+        .layer(cassette.clone())
+        .build()
+        .unwrap();
+    kv.put("users", "bob", &1).await.unwrap();
+
+    let path = cassette_path("layer");
+    cassette.save(&path).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(saved.contains("/api/users/bob"));
+    for secret in ["hunter2", "hunter3", "hunter4"] {
+        assert!(!saved.contains(secret));
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
+
+/// Adds a session cookie to every response, like an auth proxy would.
+struct SetsCookie(SharedTransport);
+
+impl Transport for SetsCookie {
+    fn send(&self, req: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        Box::pin(async move {
+            let mut resp = self.0.send(req).await?;
+            resp.headers_mut()
+                .insert("set-cookie", "session=hunter5".parse().unwrap());
+            Ok(resp)
+        })
+    }
+}
+
+#[tokio::test]
+async fn response_cookies_are_not_recorded() {
+    let server = MockServer::start().await;
+    let cassette = Cassette::record();
+    let kv = SmolKv::builder(server.url())
+        .layer(cassette.clone())
+        .layer(|inner| Arc::new(SetsCookie(inner)) as SharedTransport)
+        .build()
+        .unwrap();
+    kv.put("users", "bob", &1).await.unwrap();
+
+    let path = cassette_path("cookies");
+    cassette.save(&path).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(saved.contains("/api/users/bob"));
+    assert!(!saved.to_ascii_lowercase().contains("set-cookie"));
+    assert!(!saved.contains("hunter5"));
+
+    std::fs::remove_file(path).unwrap();
+}
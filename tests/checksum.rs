@@ -0,0 +1,166 @@
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use serde_json::json;
+use smolkv_client::checksum::{manifest_path, verify_backup_file};
+use smolkv_client::testing::MockServer;
+use smolkv_client::{Checksum, Error, SmolKv, Transport};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("smolkv-checksum-{name}-{}", fastrand::u64(..)));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Serves `data` for every backup file, advertising `header` as its checksum. Sidecar
+/// requests fail with a server error.
+struct BackupFile {
+    data: &'static str,
+    header: Option<String>,
+}
+
+impl Transport for BackupFile {
+    fn send(&self, req: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        let mut resp = http::Response::builder();
+        let resp = if req.url().path().ends_with(".sha256") {
+            resp.status(500).body("sidecar unavailable")
+        } else {
+            if let Some(header) = &self.header {
+                resp = resp.header("X-Checksum-SHA256", header);
+            }
+            resp.body(self.data)
+        };
+        Box::pin(async { Ok(resp.unwrap().into()) })
+    }
+}
+
+fn serving(data: &'static str, header: Option<String>) -> SmolKv {
+    SmolKv::builder("http://smolkv.invalid")
+        .transport(BackupFile { data, header })
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn downloads_write_a_manifest() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    server.insert("users", "bob", json!({ "age": 42 }));
+    let id = kv.start_backup("users").await.unwrap().id.unwrap();
+
+    let dir = scratch_dir("manifest");
+    let path = dir.join("latest.sst");
+    let report = kv
+        .download_backup_to_file("users", &id, &path, |_| {})
+        .await
+        .unwrap();
+    assert!(report.verified);
+
+    let manifest = manifest_path(&path);
+    assert_eq!(
+        std::fs::read_to_string(&manifest).unwrap(),
+        format!("{}  latest.sst\n", report.checksum)
+    );
+    assert_eq!(verify_backup_file(&path).await.unwrap(), report.checksum);
+    assert_eq!(
+        verify_backup_file(&manifest).await.unwrap(),
+        report.checksum
+    );
+
+    // A second copy of the same backup gets a manifest of its own.
+    let copy = dir.join("copy.sst");
+    kv.download_backup_to_file("users", &id, &copy, |_| {})
+        .await
+        .unwrap();
+    assert!(manifest_path(&copy).exists());
+    assert_eq!(
+        std::fs::read_to_string(&manifest).unwrap(),
+        format!("{}  latest.sst\n", report.checksum)
+    );
+
+    std::fs::write(&path, "tampered").unwrap();
+    let err = verify_backup_file(&manifest).await.unwrap_err();
+    assert!(matches!(err, Error::ChecksumMismatch { .. }), "{err}");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn mismatched_downloads_leave_no_file() {
+    let kv = serving("corrupted", Some(Checksum::of(b"original").to_hex()));
+    let dir = scratch_dir("mismatch");
+    let path = dir.join("users-1.sst");
+
+    let err = kv
+        .download_backup_to_file("users", "1", &path, |_| {})
+        .await
+        .unwrap_err();
+    match err {
+        Error::ChecksumMismatch { expected, actual } => {
+            assert_eq!(expected, Checksum::of(b"original").to_hex());
+            assert_eq!(actual, Checksum::of(b"corrupted").to_hex());
+        }
+        err => panic!("expected a checksum mismatch, got {err}"),
+    }
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn failing_sidecars_mean_no_checksum() {
+    let kv = serving("backup", None);
+
+    let data = kv.download_backup("users", "1").await.unwrap();
+    assert_eq!(data, "backup");
+
+    let mut buf = Vec::new();
+    let report = kv
+        .download_backup_to("users", "1", &mut buf, |_| {})
+        .await
+        .unwrap();
+    assert!(!report.verified);
+    assert_eq!(report.checksum, Checksum::of(b"backup"));
+}
+
+#[tokio::test]
+async fn backups_stream_out_and_back_in() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    server.insert("users", "bob", json!({ "age": 42 }));
+    let job = kv.start_backup("users").await.unwrap();
+    let (id, len) = (job.id.unwrap(), job.size.unwrap());
+    let original = kv.download_backup("users", &id).await.unwrap();
+    let checksum = Checksum::of(&original);
+
+    // Pipe the download straight into the upload, keeping a copy of what went through.
+    let mut download = kv
+        .download_backup_stream("users", &id, |_| {})
+        .await
+        .unwrap();
+    let (mut pipe, reader) = tokio::io::duplex(16);
+    let copy = tokio::spawn(async move {
+        let mut streamed = Vec::new();
+        while let Some(chunk) = download.next().await {
+            let chunk = chunk?;
+            pipe.write_all(&chunk).await?;
+            streamed.extend_from_slice(&chunk);
+        }
+        Ok::<_, Error>(streamed)
+    });
+    let uploaded = kv
+        .upload_backup_from("users", reader, len, Some(checksum), |_| {})
+        .await
+        .unwrap();
+    assert_eq!(copy.await.unwrap().unwrap(), original);
+
+    let uploaded_id = uploaded.id.unwrap();
+    assert_ne!(uploaded_id, id);
+    let mut data = Vec::new();
+    let report = kv
+        .download_backup_to("users", &uploaded_id, &mut data, |_| {})
+        .await
+        .unwrap();
+    assert_eq!(data, original);
+    assert!(report.verified);
+    assert_eq!(report.checksum, checksum);
+}
@@ -0,0 +1,138 @@
+use serde_json::{json, Value};
+use smolkv_client::testing::MockServer;
+use smolkv_client::Error;
+use std::collections::HashMap;
+
+/// Fixed so a failing key can be reproduced.
+const FUZZ_SEED: u64 = 0x5eed_0006;
+
+fn random_key(rng: &mut fastrand::Rng) -> String {
+    let pools = [
+        ('a'..='z').collect::<Vec<_>>(),
+        " /?#%&=+;:@!$'()*,[]\\\"<>^`{|}~\t".chars().collect(),
+        "éüñßøçЖ中文日本語🎉🚀".chars().collect(),
+    ];
+    let len = rng.usize(1..24);
+    (0..len)
+        .map(|_| {
+            let pool = &pools[rng.usize(..pools.len())];
+            pool[rng.usize(..pool.len())]
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn special_keys_round_trip() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    let keys = [
+        "plain",
+        "with/slash",
+        "a/b/c",
+        "q?x=1&y=2",
+        "hash#frag",
+        "100%",
+        "%2F",
+        "with space",
+        "plus+sign",
+        "ünïcødé",
+        "emoji 🎉",
+        "...",
+        "_batches",
+        "tab\there",
+        "line\nbreak\r",
+        "\t",
+        "back\\slash",
+    ];
+
+    for (i, key) in keys.iter().enumerate() {
+        kv.put("things", key, &json!({ "n": i })).await.unwrap();
+    }
+    for (i, key) in keys.iter().enumerate() {
+        let value: Value = kv.get("things", key).await.unwrap();
+        assert_eq!(value, json!({ "n": i }), "key {key:?}");
+    }
+
+    let stored = server.keys("things");
+    assert_eq!(stored.len(), keys.len());
+    for key in keys {
+        assert!(stored.iter().any(|k| k == key), "key {key:?}");
+    }
+}
+
+#[tokio::test]
+async fn arbitrary_utf8_keys_round_trip() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    let mut rng = fastrand::Rng::with_seed(FUZZ_SEED);
+    let mut expected = HashMap::new();
+
+    for i in 0..200 {
+        let key = random_key(&mut rng);
+        if key == "." || key == ".." {
+            continue;
+        }
+        if let Err(e) = kv.put("fuzz", &key, &i).await {
+            panic!("put {key:?} with seed {FUZZ_SEED:#x}: {e}");
+        }
+        expected.insert(key, i);
+    }
+
+    for (key, i) in expected {
+        let value: i32 = match kv.get("fuzz", &key).await {
+            Ok(value) => value,
+            Err(e) => panic!("get {key:?} with seed {FUZZ_SEED:#x}: {e}"),
+        };
+        assert_eq!(value, i, "key {key:?} with seed {FUZZ_SEED:#x}");
+    }
+}
+
+#[tokio::test]
+async fn collection_names_are_encoded() {
+    let server = MockServer::start().await;
+    server
+        .client()
+        .put("my/collection", "key", &1)
+        .await
+        .unwrap();
+
+    assert_eq!(server.value("my/collection", "key"), Some(json!(1)));
+}
+
+#[tokio::test]
+async fn query_parameters_are_encoded() {
+    let server = MockServer::start().await;
+    let err = server
+        .client()
+        .backup_status("things", "a&b=c #1")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::NotFound(_)), "{err}");
+
+    let request = server.requests().pop().unwrap();
+    assert_eq!(request.path, "/api/things/_backup/status");
+    assert_eq!(request.query.as_deref(), Some("id=a%26b%3Dc+%231"));
+}
+
+#[tokio::test]
+async fn dot_segments_are_rejected() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+
+    for key in ["", ".", ".."] {
+        let err = kv.get::<Value>("things", key).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidInput(_)), "key {key:?}: {err}");
+    }
+}
+
+#[tokio::test]
+async fn reserved_keys_are_rejected() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+
+    for key in ["_batch", "_subscribe", "_import", "_backup", "_restore"] {
+        let err = kv.put("things", key, &1).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidInput(_)), "key {key:?}: {err}");
+    }
+    assert!(server.requests().is_empty());
+}
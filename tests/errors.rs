@@ -0,0 +1,62 @@
+use serde_json::{json, Value};
+use smolkv_client::testing::{Fault, MockServer};
+use smolkv_client::{Error, RetryPolicy, SmolKv};
+use std::time::Duration;
+
+fn no_retries(server: &MockServer) -> SmolKv {
+    SmolKv::builder(server.url())
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap()
+}
+
+/// The error a GET of `users/bob` fails with when the server answers with `fault`.
+async fn error_for(fault: Fault) -> Error {
+    let server = MockServer::start().await;
+    server.inject(fault);
+    no_retries(&server)
+        .get::<Value>("users", "bob")
+        .await
+        .unwrap_err()
+}
+
+#[tokio::test]
+async fn statuses_map_to_variants() {
+    for status in [401, 403, 408, 413, 429, 503, 504] {
+        let err = error_for(Fault::status(status)).await;
+        let (mapped, retryable) = match &err {
+            Error::Unauthorized(_) => (status == 401, false),
+            Error::Forbidden(_) => (status == 403, false),
+            Error::Timeout(_) => (status == 408 || status == 504, true),
+            Error::PayloadTooLarge(_) => (status == 413, false),
+            Error::RateLimited { .. } => (status == 429, true),
+            Error::Unavailable { .. } => (status == 503, true),
+            _ => (false, false),
+        };
+        assert!(mapped, "{status}: {err}");
+        assert_eq!(err.status().map(|s| s.as_u16()), Some(status));
+        assert_eq!(err.is_retryable(), retryable, "{status}");
+        assert_eq!(err.retry_after(), None, "{status}");
+    }
+}
+
+#[tokio::test]
+async fn retry_after_is_kept() {
+    for status in [429, 503] {
+        let err = error_for(Fault::status(status).retry_after(7)).await;
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(7)), "{status}");
+    }
+}
+
+#[tokio::test]
+async fn contexts_keep_the_request_and_body() {
+    let err = error_for(Fault::status(413)).await;
+    let context = err.context().unwrap();
+    assert_eq!(context.method, reqwest::Method::GET);
+    assert_eq!(context.path, "/api/users/bob");
+    assert_eq!(context.collection.as_deref(), Some("users"));
+    assert_eq!(context.key.as_deref(), Some("bob"));
+    assert_eq!(context.body, Some(json!({ "error": "injected fault" })));
+    assert_eq!(context.message().as_deref(), Some("injected fault"));
+    assert!(err.to_string().ends_with("injected fault"), "{err}");
+}
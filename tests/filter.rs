@@ -0,0 +1,121 @@
+use smolkv_client::{Error, Filter, QueryBuilder};
+
+fn render(filter: Filter) -> String {
+    filter.to_jsonpath().unwrap()
+}
+
+#[test]
+fn renders_comparisons() {
+    assert_eq!(
+        render(Filter::field("name").eq("bob")),
+        r#"$[?(@.name == "bob")]"#
+    );
+    assert_eq!(render(Filter::field("stars").ne(3)), "$[?(@.stars != 3)]");
+    assert_eq!(render(Filter::field("stars").gt(10)), "$[?(@.stars > 10)]");
+    assert_eq!(
+        render(Filter::field("stars").ge(10u64)),
+        "$[?(@.stars >= 10)]"
+    );
+    assert_eq!(
+        render(Filter::field("score").lt(0.5)),
+        "$[?(@.score < 0.5)]"
+    );
+    assert_eq!(render(Filter::field("score").le(-2)), "$[?(@.score <= -2)]");
+    assert_eq!(
+        render(Filter::field("active").eq(true)),
+        "$[?(@.active == true)]"
+    );
+    assert_eq!(
+        render(Filter::field("deleted_at").eq(None::<i64>)),
+        "$[?(@.deleted_at == null)]"
+    );
+    assert_eq!(render(Filter::field("email").exists()), "$[?(@.email)]");
+}
+
+#[test]
+fn renders_nested_fields() {
+    assert_eq!(
+        render(Filter::field("owner.login").eq("bob")),
+        r#"$[?(@.owner.login == "bob")]"#
+    );
+    assert_eq!(
+        render(Filter::field_path(["meta", "first name"]).eq("Ann")),
+        r#"$[?(@.meta["first name"] == "Ann")]"#
+    );
+    assert_eq!(
+        render(Filter::field_path(["a.b", "0"]).exists()),
+        r#"$[?(@["a.b"]["0"])]"#
+    );
+}
+
+#[test]
+fn escapes_string_literals() {
+    assert_eq!(
+        render(Filter::field("title").eq(r#"say "hi" \ bye"#)),
+        r#"$[?(@.title == "say \"hi\" \\ bye")]"#
+    );
+    assert_eq!(
+        render(Filter::field("title").eq("it's\nfine")),
+        r#"$[?(@.title == "it's\nfine")]"#
+    );
+    assert_eq!(
+        render(Filter::field("title").eq("]) || true || ([")),
+        r#"$[?(@.title == "]) || true || ([")]"#
+    );
+}
+
+#[test]
+fn combines_expressions() {
+    let filter = Filter::field("owner.login")
+        .eq("bob")
+        .and(Filter::field("stars").gt(10));
+    assert_eq!(
+        render(filter),
+        r#"$[?(@.owner.login == "bob" && @.stars > 10)]"#
+    );
+
+    let filter = Filter::field("a")
+        .eq(1)
+        .or(Filter::field("b").eq(2))
+        .and(Filter::field("c").eq(3));
+    assert_eq!(render(filter), "$[?((@.a == 1 || @.b == 2) && @.c == 3)]");
+
+    let filter = Filter::field("a")
+        .eq(1)
+        .and(Filter::field("b").eq(2))
+        .and(Filter::field("c").eq(3));
+    assert_eq!(render(filter), "$[?(@.a == 1 && @.b == 2 && @.c == 3)]");
+
+    let filter = !Filter::field("archived").eq(true);
+    assert_eq!(render(filter), "$[?(!(@.archived == true))]");
+}
+
+#[test]
+fn rejects_invalid_expressions() {
+    for filter in [
+        Filter::field("").eq(1),
+        Filter::field("owner..login").eq(1),
+        Filter::field_path(Vec::<String>::new()).exists(),
+        Filter::field("score").gt(f64::NAN),
+        Filter::field("score").lt(f64::INFINITY),
+        Filter::field("a").eq(1).and(Filter::field("b.").eq(2)),
+    ] {
+        assert!(
+            matches!(filter.to_jsonpath(), Err(Error::InvalidInput(_))),
+            "{filter:?}"
+        );
+    }
+}
+
+#[test]
+fn sets_query_builder_query() {
+    let query = QueryBuilder::new()
+        .filter(&Filter::field("stars").gt(10))
+        .unwrap();
+    let json = serde_json::to_value(&query).unwrap();
+    assert_eq!(json["query"], "$[?(@.stars > 10)]");
+
+    assert!(QueryBuilder::new()
+        .filter(&Filter::field("").eq(1))
+        .is_err());
+}
@@ -0,0 +1,94 @@
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use smolkv_client::{BatchOperation, Error, KvStore, MemoryKv, QueryBuilder, SortOrder};
+
+async fn seed(kv: &impl KvStore) {
+    let items: Vec<_> = ["a", "b", "c", "d", "e"]
+        .iter()
+        .enumerate()
+        .map(|(i, key)| BatchOperation {
+            key: key.to_string(),
+            value: json!({ "n": i }),
+        })
+        .collect();
+    kv.batch_put("letters", &items).await.unwrap();
+}
+
+#[tokio::test]
+async fn key_operations() {
+    let kv = MemoryKv::new();
+    kv.put("users", "bob", &json!({ "age": 42 })).await.unwrap();
+
+    let value: Value = kv.get("users", "bob").await.unwrap();
+    assert_eq!(value, json!({ "age": 42 }));
+    assert!(kv.exists("users", "bob").await.unwrap());
+    assert!(matches!(
+        kv.get::<Value>("users", "alice").await,
+        Err(Error::NotFound(_))
+    ));
+
+    assert!(kv.delete("users", "bob").await.unwrap());
+    assert!(!kv.delete("users", "bob").await.unwrap());
+    assert!(!kv.exists("users", "bob").await.unwrap());
+}
+
+#[tokio::test]
+async fn query_honors_range_order_and_limit() {
+    let kv = MemoryKv::new();
+    seed(&kv).await;
+
+    let all = kv.query("letters", QueryBuilder::new()).await.unwrap();
+    assert_eq!(all.len(), 5);
+    assert_eq!(all[0], json!({ "n": 0 }));
+
+    let range = QueryBuilder::new().from(Some("b")).to(Some("d")).keys(true);
+    let keys: Vec<_> = kv
+        .query("letters", range)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|item| item["key"].clone())
+        .collect();
+    assert_eq!(keys, [json!("b"), json!("c"), json!("d")]);
+
+    let desc = QueryBuilder::new()
+        .order(SortOrder::Desc)
+        .limit(Some(2))
+        .keys(true);
+    let items = kv.query("letters", desc).await.unwrap();
+    assert_eq!(
+        items,
+        [
+            json!({ "key": "e", "value": { "n": 4 } }),
+            json!({ "key": "d", "value": { "n": 3 } }),
+        ]
+    );
+
+    assert!(matches!(
+        kv.query("missing", QueryBuilder::new()).await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        kv.query("letters", QueryBuilder::new().query("$[?(@.n > 1)]"))
+            .await,
+        Err(Error::InvalidInput(_))
+    ));
+}
+
+#[tokio::test]
+async fn subscribers_receive_events() {
+    let kv = MemoryKv::new();
+    let mut events = kv.subscribe("users");
+
+    kv.put("users", "bob", &json!(1)).await.unwrap();
+    kv.put("other", "bob", &json!(2)).await.unwrap();
+    kv.delete("users", "bob").await.unwrap();
+
+    let put = events.next().await.unwrap().unwrap();
+    assert_eq!((put.operation.as_str(), put.key.as_str()), ("put", "bob"));
+    assert_eq!(put.value, json!(1));
+
+    let delete = events.next().await.unwrap().unwrap();
+    assert_eq!(delete.operation, "delete");
+    assert_eq!(delete.value, Value::Null);
+}
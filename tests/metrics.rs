@@ -0,0 +1,180 @@
+#![cfg(feature = "metrics")]
+
+use futures_util::StreamExt;
+use serde_json::json;
+use smolkv_client::testing::{Fault, MockServer};
+use smolkv_client::{BatchOperation, MetricsSink, RequestStats, RetryPolicy, SmolKv};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+struct Request {
+    collection: Option<String>,
+    operation: &'static str,
+    status: Option<u16>,
+    success: bool,
+    retries: u32,
+    request_bytes: u64,
+}
+
+/// Collection, operation and size of a bulk operation.
+type Batch = (String, &'static str, usize);
+
+/// Keeps everything it is sent in memory.
+#[derive(Default, Clone)]
+struct Collector {
+    requests: Arc<Mutex<Vec<Request>>>,
+    events: Arc<Mutex<Vec<String>>>,
+    reconnects: Arc<Mutex<Vec<String>>>,
+    batches: Arc<Mutex<Vec<Batch>>>,
+}
+
+impl MetricsSink for Collector {
+    fn record_request(&self, stats: &RequestStats<'_>) {
+        self.requests.lock().unwrap().push(Request {
+            collection: stats.collection.map(str::to_string),
+            operation: stats.operation,
+            status: stats.status,
+            success: stats.success,
+            retries: stats.retries,
+            request_bytes: stats.request_bytes,
+        });
+    }
+
+    fn record_subscribe_event(&self, collection: &str) {
+        self.events.lock().unwrap().push(collection.to_string());
+    }
+
+    fn record_reconnect(&self, collection: &str) {
+        self.reconnects.lock().unwrap().push(collection.to_string());
+    }
+
+    fn record_batch(&self, collection: &str, operation: &'static str, size: usize) {
+        self.batches
+            .lock()
+            .unwrap()
+            .push((collection.to_string(), operation, size));
+    }
+}
+
+fn client(server: &MockServer, collector: &Collector) -> SmolKv {
+    SmolKv::builder(server.url())
+        .retry_policy(RetryPolicy::new().base_delay(Duration::from_millis(5)))
+        .metrics_sink(collector.clone())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn requests_are_recorded() {
+    let server = MockServer::start().await;
+    let collector = Collector::default();
+    let kv = client(&server, &collector);
+
+    server.inject(Fault::status(503).times(1));
+    kv.put("users", "bob", &json!({ "age": 42 })).await.unwrap();
+    kv.get::<serde_json::Value>("users", "bob").await.unwrap();
+    assert!(kv.get::<serde_json::Value>("users", "alice").await.is_err());
+
+    let requests = collector.requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+
+    let put = &requests[0];
+    assert_eq!(put.collection.as_deref(), Some("users"));
+    assert_eq!(put.operation, "put");
+    assert_eq!((put.status, put.success, put.retries), (Some(200), true, 1));
+    let body = serde_json::to_vec(&json!({ "age": 42 })).unwrap();
+    assert_eq!(put.request_bytes, 2 * body.len() as u64);
+
+    assert_eq!(requests[1].operation, "get");
+    assert_eq!((requests[1].status, requests[1].retries), (Some(200), 0));
+    assert_eq!(requests[2].operation, "get");
+    assert_eq!(
+        (requests[2].status, requests[2].success),
+        (Some(404), false)
+    );
+}
+
+#[tokio::test]
+async fn batch_sizes_are_recorded() {
+    let server = MockServer::start().await;
+    let collector = Collector::default();
+    let kv = client(&server, &collector);
+
+    let items: Vec<_> = ["a", "b", "c"]
+        .into_iter()
+        .map(|key| BatchOperation {
+            key: key.to_string(),
+            value: 1,
+        })
+        .collect();
+    kv.batch_put("users", &items).await.unwrap();
+    kv.batch_delete("users", &["a", "b"]).await.unwrap();
+
+    let batches = collector.batches.lock().unwrap();
+    assert_eq!(
+        *batches,
+        [
+            ("users".to_string(), "batch_put", 3),
+            ("users".to_string(), "batch_delete", 2),
+        ]
+    );
+    let requests = collector.requests.lock().unwrap();
+    assert_eq!(requests[0].operation, "batch_put");
+}
+
+#[tokio::test]
+async fn subscriptions_are_recorded() {
+    let server = MockServer::start().await;
+    let collector = Collector::default();
+    let kv = client(&server, &collector);
+    server.insert("users", "bob", json!(1));
+
+    server.inject(Fault::drop_stream(1).times(1));
+    let mut events = kv.subscribe_events("users");
+    events.next().await.unwrap().unwrap();
+    server.insert("users", "alice", json!(2));
+    events.next().await.unwrap().unwrap();
+
+    assert_eq!(*collector.events.lock().unwrap(), ["users", "users"]);
+    assert_eq!(*collector.reconnects.lock().unwrap(), ["users"]);
+    let requests = collector.requests.lock().unwrap();
+    assert!(requests.iter().all(|r| r.operation == "subscribe"));
+}
+
+#[tokio::test]
+async fn operations_are_named_after_the_method() {
+    let server = MockServer::start().await;
+    let collector = Collector::default();
+    let kv = client(&server, &collector);
+
+    kv.put_if_absent("users", "bob", &1).await.unwrap();
+    kv.get_with_version::<i32>("users", "bob").await.unwrap();
+    kv.put_with_ttl("users", "bob", &2, Duration::from_secs(60))
+        .await
+        .unwrap();
+    kv.ttl("users", "bob").await.unwrap();
+    let id = kv.start_backup("users").await.unwrap().id.unwrap();
+    kv.download_backup("users", &id).await.unwrap();
+
+    let operations: Vec<_> = collector
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|r| r.operation)
+        .collect();
+    assert_eq!(
+        operations[..6],
+        [
+            "put_if_absent",
+            "get_with_version",
+            "put_with_ttl",
+            "ttl",
+            "start_backup",
+            "download_backup",
+        ]
+    );
+    // The checksum sidecar is counted on its own, not as a second download.
+    assert!(operations[6..].iter().all(|op| *op == "backup_checksum"));
+}
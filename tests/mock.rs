@@ -0,0 +1,811 @@
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use smolkv_client::testing::{Fault, MockServer};
+use smolkv_client::{
+    BatchMode, BatchOperation, Error, JobState, Op, QueryBuilder, RetryPolicy, SmolKv, SortOrder,
+    Transport, WaitOptions,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn fast_retries(server: &MockServer) -> SmolKv {
+    SmolKv::builder(server.url())
+        .retry_policy(
+            RetryPolicy::new()
+                .max_attempts(4)
+                .base_delay(Duration::from_millis(5))
+                .jitter(false),
+        )
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn key_operations() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+
+    kv.put("users", "bob", &json!({ "age": 42 })).await.unwrap();
+    let value: Value = kv.get("users", "bob").await.unwrap();
+    assert_eq!(value, json!({ "age": 42 }));
+    assert!(kv.exists("users", "bob").await.unwrap());
+    assert!(matches!(
+        kv.get::<Value>("users", "alice").await,
+        Err(Error::NotFound(_))
+    ));
+
+    assert!(kv.delete("users", "bob").await.unwrap());
+    assert!(!kv.delete("users", "bob").await.unwrap());
+    assert!(server.keys("users").is_empty());
+}
+
+#[tokio::test]
+async fn collections_and_queries() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+
+    kv.create_collection("letters").await.unwrap();
+    assert!(kv.collection_exists("letters").await.unwrap());
+    assert!(kv.create_collection("letters").await.is_err());
+
+    let items: Vec<_> = ["a", "b", "c", "d"]
+        .iter()
+        .enumerate()
+        .map(|(i, key)| BatchOperation {
+            key: key.to_string(),
+            value: json!({ "n": i }),
+        })
+        .collect();
+    kv.batch_put("letters", &items).await.unwrap();
+
+    let query = QueryBuilder::new()
+        .from(Some("b"))
+        .order(SortOrder::Desc)
+        .limit(Some(2))
+        .keys(true);
+    let found = kv.query_collection("letters", query).await.unwrap();
+    assert_eq!(
+        found,
+        [
+            json!({ "key": "d", "value": { "n": 3 } }),
+            json!({ "key": "c", "value": { "n": 2 } }),
+        ]
+    );
+    assert_eq!(
+        kv.list_collection("letters", QueryBuilder::new())
+            .await
+            .unwrap()
+            .len(),
+        4
+    );
+
+    kv.drop_collection("letters").await.unwrap();
+    assert!(!kv.collection_exists("letters").await.unwrap());
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+    age: u32,
+}
+
+fn user(name: &str, age: u32) -> User {
+    User {
+        name: name.to_string(),
+        age,
+    }
+}
+
+#[tokio::test]
+async fn typed_collections() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    let users = kv.collection::<User>("users");
+    let mut events = users.subscribe();
+
+    users.put("alice", &user("alice", 30)).await.unwrap();
+    assert_eq!(users.get("alice").await.unwrap(), user("alice", 30));
+    assert!(users.exists("alice").await.unwrap());
+
+    let items = [user("bob", 42), user("carol", 7)].map(|u| BatchOperation {
+        key: u.name.clone(),
+        value: u,
+    });
+    users.batch_put(&items).await.unwrap();
+    assert_eq!(
+        users
+            .query(QueryBuilder::new().from(Some("bob")))
+            .await
+            .unwrap(),
+        [user("bob", 42), user("carol", 7)]
+    );
+    assert_eq!(users.list(QueryBuilder::new()).await.unwrap().len(), 3);
+
+    assert!(users.delete("alice").await.unwrap());
+    assert!(!users.exists("alice").await.unwrap());
+    assert!(matches!(users.get("alice").await, Err(Error::NotFound(_))));
+
+    let mut seen = Vec::new();
+    while seen.len() < 4 {
+        let event = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .expect("timed out waiting for events")
+            .unwrap()
+            .unwrap();
+        seen.push((event.operation, event.key, event.value));
+    }
+    assert_eq!(
+        seen,
+        [
+            (
+                "put".to_string(),
+                "alice".to_string(),
+                Some(user("alice", 30))
+            ),
+            ("put".to_string(), "bob".to_string(), Some(user("bob", 42))),
+            (
+                "put".to_string(),
+                "carol".to_string(),
+                Some(user("carol", 7))
+            ),
+            ("delete".to_string(), "alice".to_string(), None),
+        ]
+    );
+
+    // Writes only need `T: Serialize`, so borrowed values work too.
+    kv.collection::<&str>("names")
+        .put("a", &"alice")
+        .await
+        .unwrap();
+    assert_eq!(server.value("names", "a"), Some(json!("alice")));
+}
+
+fn numbered_keys(server: &MockServer, n: usize) -> Vec<String> {
+    let keys: Vec<_> = (0..n).map(|i| format!("k{i:02}")).collect();
+    for (i, key) in keys.iter().enumerate() {
+        server.insert("numbers", key, json!(i));
+    }
+    keys
+}
+
+async fn stream_keys(kv: &SmolKv, query: QueryBuilder, page_size: usize) -> Vec<String> {
+    kv.query_stream::<i32>("numbers", query, page_size)
+        .map(|item| item.unwrap().0)
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn query_streams_cross_page_boundaries_once() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    let keys = numbered_keys(&server, 10);
+
+    for page_size in [1, 3, 5, 10, 20] {
+        let streamed = stream_keys(&kv, QueryBuilder::new(), page_size).await;
+        assert_eq!(streamed, keys, "page size {page_size}");
+    }
+}
+
+#[tokio::test]
+async fn query_streams_descend() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    let keys = numbered_keys(&server, 10);
+
+    let query = QueryBuilder::new().from(Some("k02")).order(SortOrder::Desc);
+    let streamed = stream_keys(&kv, query, 3).await;
+    let expected: Vec<_> = keys[2..].iter().rev().cloned().collect();
+    assert_eq!(streamed, expected);
+}
+
+#[tokio::test]
+async fn query_streams_stop_at_the_limit() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    let keys = numbered_keys(&server, 10);
+
+    let streamed = stream_keys(&kv, QueryBuilder::new().limit(Some(5)), 2).await;
+    assert_eq!(streamed, keys[..5]);
+
+    let query = QueryBuilder::new().order(SortOrder::Desc).limit(Some(4));
+    let streamed = stream_keys(&kv, query, 3).await;
+    assert_eq!(streamed, ["k09", "k08", "k07", "k06"]);
+
+    let queries = server
+        .requests()
+        .iter()
+        .filter(|req| req.path == "/api/numbers")
+        .count();
+    assert_eq!(queries, 5);
+}
+
+#[tokio::test]
+async fn conditional_writes() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+
+    kv.put_if_absent("counters", "hits", &1).await.unwrap();
+    assert!(matches!(
+        kv.put_if_absent("counters", "hits", &2).await,
+        Err(Error::Conflict(_))
+    ));
+
+    let current = kv
+        .get_with_version::<i32>("counters", "hits")
+        .await
+        .unwrap();
+    let version = current.version.unwrap();
+    kv.put_if_match("counters", "hits", &2, &version)
+        .await
+        .unwrap();
+    assert!(matches!(
+        kv.put_if_match("counters", "hits", &3, &version).await,
+        Err(Error::Conflict(_))
+    ));
+
+    let updated = kv
+        .update("counters", "hits", |n: Option<i32>| n.unwrap_or(0) + 1)
+        .await
+        .unwrap();
+    assert_eq!(updated, 3);
+}
+
+#[tokio::test]
+async fn batches_use_server_endpoints() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    server.insert("users", "bob", json!(1));
+
+    let outcome = kv
+        .batch(
+            "users",
+            &[
+                Op::put("alice", json!(2)),
+                Op::put_if_absent("bob", json!(3)),
+                Op::delete("carol"),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(outcome.mode, BatchMode::Server);
+    assert_eq!(outcome.report.succeeded, ["alice", "carol"]);
+    assert_eq!(outcome.report.failed[0].key, "bob");
+
+    let got = kv
+        .batch_get::<i32>("users", &["alice", "bob", "dave"])
+        .await
+        .unwrap();
+    assert_eq!(got.values["alice"], Some(2));
+    assert_eq!(got.values["bob"], Some(1));
+    assert_eq!(got.values["dave"], None);
+
+    let deleted = kv.batch_delete("users", &["alice", "bob"]).await.unwrap();
+    assert!(deleted.is_success());
+    assert!(server.keys("users").is_empty());
+    assert!(server
+        .requests()
+        .iter()
+        .all(|req| !req.path.starts_with("/api/users/alice")));
+}
+
+/// Answers every request with the same JSON body, like a server with its own reply
+/// format.
+struct Reply(&'static str);
+
+impl Transport for Reply {
+    fn send(&self, _: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        Box::pin(async { Ok(http::Response::new(self.0).into()) })
+    }
+}
+
+/// Replies like a server whose status replies leave out the job id.
+#[derive(Default)]
+struct BackupReplies(std::sync::Mutex<Vec<String>>);
+
+impl Transport for BackupReplies {
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        let path = request.url().path().to_string();
+        self.0.lock().unwrap().push(path.clone());
+        let reply = if path.ends_with("/_backup") {
+            http::Response::new(r#"{"id": "b1", "status": "running"}"#)
+        } else if path.ends_with("/status") {
+            http::Response::new(r#"{"status": "completed"}"#)
+        } else if path.ends_with(".sst") {
+            http::Response::new("backup")
+        } else {
+            http::Response::builder().status(404).body("").unwrap()
+        };
+        Box::pin(async move { Ok(reply.into()) })
+    }
+}
+
+#[tokio::test]
+async fn backup_and_download_keeps_the_started_id() {
+    let transport = Arc::new(BackupReplies::default());
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(transport.clone())
+        .build()
+        .unwrap();
+
+    assert_eq!(kv.backup_and_download("users").await.unwrap(), "backup");
+    assert!(transport
+        .0
+        .lock()
+        .unwrap()
+        .contains(&"/backups/users-b1.sst".to_string()));
+
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(Reply(r#"{"status": "running"}"#))
+        .build()
+        .unwrap();
+    let err = kv.backup_and_download("users").await.unwrap_err();
+    assert!(matches!(err, Error::MissingJobId { .. }), "{err}");
+}
+
+#[tokio::test]
+async fn waits_poll_until_the_deadline() {
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(Reply(r#"{"id": "b1", "status": "running"}"#))
+        .build()
+        .unwrap();
+    let polls = Arc::new(AtomicUsize::new(0));
+    let counter = polls.clone();
+    let opts = WaitOptions::new()
+        .interval(Duration::from_secs(10))
+        .timeout(Some(Duration::from_millis(100)))
+        .on_progress(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+    let started = Instant::now();
+    let err = kv.wait_for_backup("users", "b1", opts).await.unwrap_err();
+    assert!(matches!(err, Error::JobTimeout { .. }), "{err}");
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn subscriptions_skip_other_event_types() {
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(Reply(concat!(
+            "event: ping\ndata: keepalive\n\n",
+            "event: change\ndata: {\"operation\": \"put\", \"key\": \"a\"}\n\n",
+            "data: {\"operation\": \"delete\", \"key\": \"b\"}\n\n",
+        )))
+        .build()
+        .unwrap();
+
+    let events: Vec<_> = kv.subscribe_events("users").take(2).collect().await;
+    let keys: Vec<_> = events.into_iter().map(|e| e.unwrap().key).collect();
+    assert_eq!(keys, ["a", "b"]);
+}
+
+#[tokio::test]
+async fn batch_delete_reads_other_reply_shapes() {
+    let keys = ["alice", "bob"];
+    let kv = |reply| {
+        SmolKv::builder("http://smolkv.invalid")
+            .transport(Reply(reply))
+            .build()
+            .unwrap()
+    };
+
+    let report = kv(r#"{"message": "ok"}"#)
+        .batch_delete("users", &keys)
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded, keys);
+    assert!(report.is_success());
+
+    let report = kv(r#"{"failed": [{"key": "bob", "error": "locked"}]}"#)
+        .batch_delete("users", &keys)
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded, ["alice"]);
+    assert_eq!(report.failed[0].key, "bob");
+}
+
+#[tokio::test]
+async fn jobs_without_an_id_report_none() {
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(Reply(r#"{"status": "pending"}"#))
+        .build()
+        .unwrap();
+
+    let backup = kv.start_backup("users").await.unwrap();
+    assert_eq!((backup.id, backup.status), (None, JobState::Pending));
+    let restore = kv.start_restore("users", "1").await.unwrap();
+    assert_eq!(restore.id, None);
+}
+
+#[tokio::test]
+async fn job_replies_without_a_status_fail() {
+    for reply in ["", r#"{"id": "b1"}"#] {
+        let kv = SmolKv::builder("http://smolkv.invalid")
+            .transport(Reply(reply))
+            .build()
+            .unwrap();
+        let err = kv.backup_status("users", "b1").await.unwrap_err();
+        assert!(matches!(err, Error::Json(_)), "{reply}: {err}");
+        let err = kv
+            .wait_for_backup("users", "b1", WaitOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Json(_)), "{reply}: {err}");
+    }
+}
+
+#[tokio::test]
+async fn import_replies_may_report_success_as_a_flag() {
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(Reply(r#"{"success": true}"#))
+        .build()
+        .unwrap();
+    let report = kv
+        .import_values("things", None, b"[]".to_vec())
+        .await
+        .unwrap();
+    assert_eq!((report.imported, report.failed), (0, 0));
+    assert_eq!(report.extra["success"], true);
+}
+
+#[tokio::test]
+async fn retries_injected_failures() {
+    let server = MockServer::start().await;
+    let kv = fast_retries(&server);
+    server.insert("users", "bob", json!(1));
+    server.inject(Fault::status(503).method("GET").times(2));
+
+    let value: i32 = kv.get("users", "bob").await.unwrap();
+    assert_eq!(value, 1);
+    assert_eq!(server.requests().len(), 3);
+
+    server.inject(Fault::status(500).path("/api/users/bob"));
+    let err = kv.get::<i32>("users", "bob").await.unwrap_err();
+    assert!(err.is_retryable(), "{err}");
+    assert_eq!(server.requests().len(), 7);
+
+    server.clear_faults();
+    server.inject(Fault::latency(Duration::from_millis(100)).times(1));
+    let started = Instant::now();
+    kv.exists("users", "bob").await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn retry_after_is_capped_at_the_max_delay() {
+    let server = MockServer::start().await;
+    let kv = SmolKv::builder(server.url())
+        .retry_policy(RetryPolicy::new().max_delay(Duration::from_millis(50)))
+        .build()
+        .unwrap();
+    server.insert("users", "bob", json!(1));
+    server.inject(Fault::status(503).retry_after(60).times(1));
+
+    let started = Instant::now();
+    let value: i32 = kv.get("users", "bob").await.unwrap();
+    assert_eq!(value, 1);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn subscriptions_resume_after_dropped_streams() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    server.inject(Fault::drop_stream(1).path("/api/users/_subscribe").times(1));
+
+    let mut events = kv.subscribe_events("users");
+    let writer = kv.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        for i in 0..3 {
+            writer.put("users", &format!("user{i}"), &i).await.unwrap();
+        }
+    });
+
+    let mut keys = Vec::new();
+    while keys.len() < 3 {
+        let event = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .expect("timed out waiting for events")
+            .unwrap()
+            .unwrap();
+        keys.push(event.key);
+    }
+    assert_eq!(keys, ["user0", "user1", "user2"]);
+
+    let subscribes = server
+        .requests()
+        .iter()
+        .filter(|req| req.path == "/api/users/_subscribe")
+        .count();
+    assert_eq!(subscribes, 2);
+}
+
+#[tokio::test]
+async fn subscriptions_outlive_the_request_timeout() {
+    let server = MockServer::start().await;
+    let kv = SmolKv::builder(server.url())
+        .timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    let mut events = kv.subscribe_events("users");
+    let writer = server.client();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        writer.put("users", "late", &1).await.unwrap();
+    });
+
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next())
+        .await
+        .expect("timed out waiting for events")
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.key, "late");
+    let subscribes = server
+        .requests()
+        .iter()
+        .filter(|req| req.path == "/api/users/_subscribe")
+        .count();
+    assert_eq!(subscribes, 1);
+}
+
+/// A change feed that sends one event with a `retry: 0` hint per connection.
+#[derive(Default)]
+struct ZeroRetryFeed {
+    opened: AtomicUsize,
+}
+
+impl Transport for ZeroRetryFeed {
+    fn send(&self, _: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        self.opened.fetch_add(1, Ordering::SeqCst);
+        let body = "retry: 0\ndata: {\"operation\": \"put\", \"key\": \"k\"}\n\n";
+        let resp = http::Response::builder()
+            .header("content-type", "text/event-stream")
+            .body(body)
+            .unwrap();
+        Box::pin(async { Ok(resp.into()) })
+    }
+}
+
+#[tokio::test]
+async fn zero_retry_hints_are_clamped() {
+    let feed = Arc::new(ZeroRetryFeed::default());
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(feed.clone())
+        .build()
+        .unwrap();
+
+    let events = kv.subscribe_events("users");
+    let received = events
+        .take_until(tokio::time::sleep(Duration::from_millis(300)))
+        .count()
+        .await;
+    assert!(received >= 2, "{received} events");
+    assert!(feed.opened.load(Ordering::SeqCst) < 20);
+}
+
+/// A change feed whose events carry a server time but no event id, closing after one
+/// event. Records the resume headers and query of every connection.
+#[derive(Default)]
+struct TimedFeed {
+    opened: std::sync::Mutex<Vec<(Option<String>, Option<String>)>>,
+}
+
+impl Transport for TimedFeed {
+    fn send(&self, req: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        let last_event_id = req
+            .headers()
+            .get("last-event-id")
+            .map(|v| v.to_str().unwrap().to_string());
+        let query = req.url().query().map(str::to_string);
+        self.opened.lock().unwrap().push((last_event_id, query));
+        let body =
+            "retry: 10\ndata: {\"operation\": \"put\", \"key\": \"k\", \"server_time\": 7}\n\n";
+        let resp = http::Response::builder()
+            .header("content-type", "text/event-stream")
+            .body(body)
+            .unwrap();
+        Box::pin(async { Ok(resp.into()) })
+    }
+}
+
+#[tokio::test]
+async fn subscriptions_resume_from_the_server_time() {
+    let feed = Arc::new(TimedFeed::default());
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(feed.clone())
+        .build()
+        .unwrap();
+
+    let events: Vec<_> = kv.subscribe_events("users").take(2).collect().await;
+    assert!(events.iter().all(Result::is_ok));
+    let opened = feed.opened.lock().unwrap();
+    assert_eq!(opened[0], (None, None));
+    assert_eq!(opened[1], (None, Some("since=7".to_string())));
+}
+
+#[tokio::test]
+async fn backup_and_restore_round_trip() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    server.insert("users", "bob", json!({ "age": 42 }));
+
+    let backup = kv.backup_and_download("users").await.unwrap();
+    kv.delete("users", "bob").await.unwrap();
+
+    let uploaded = kv.upload_backup("users", backup.to_vec()).await.unwrap();
+    let restore = kv
+        .start_restore("users", uploaded.id.as_deref().unwrap())
+        .await
+        .unwrap();
+    let restore = kv
+        .wait_for_restore(
+            "users",
+            restore.id.as_deref().unwrap(),
+            WaitOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(restore.status, JobState::Completed);
+    assert_eq!(server.value("users", "bob"), Some(json!({ "age": 42 })));
+}
+
+#[tokio::test]
+async fn imports_json_arrays() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+
+    let values = json!([{ "id": "a", "n": 1 }, { "id": "b", "n": 2 }, { "n": 3 }]);
+    let report = kv
+        .import_values("things", Some("id".into()), values.to_string().into_bytes())
+        .await
+        .unwrap();
+    assert_eq!((report.imported, report.failed), (2, 1));
+    assert_eq!(server.keys("things"), ["a", "b"]);
+}
+
+#[tokio::test]
+async fn imports_stream_from_readers() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+    let values = json!([{ "id": "a", "n": 1 }, { "id": "b", "n": 2 }]).to_string();
+    let len = values.len() as u64;
+    let transferred = Arc::new(AtomicUsize::new(0));
+
+    let seen = transferred.clone();
+    let report = kv
+        .import_values_from(
+            "things",
+            Some("id".into()),
+            std::io::Cursor::new(values.into_bytes()),
+            len,
+            move |progress| seen.store(progress.transferred as usize, Ordering::SeqCst),
+        )
+        .await
+        .unwrap();
+    assert_eq!((report.imported, report.failed), (2, 0));
+    assert_eq!(server.keys("things"), ["a", "b"]);
+    assert_eq!(transferred.load(Ordering::SeqCst) as u64, len);
+}
+
+#[tokio::test]
+async fn streamed_bodies_fail_without_waiting_to_retry() {
+    let server = MockServer::start().await;
+    server.inject(Fault::status(503).times(1));
+    let kv = SmolKv::builder(server.url())
+        .retry_policy(
+            RetryPolicy::new()
+                .retry_non_idempotent(true)
+                .base_delay(Duration::from_secs(10))
+                .jitter(false),
+        )
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    let err = kv
+        .import_values_from(
+            "things",
+            None,
+            std::io::Cursor::new(b"[]".to_vec()),
+            2,
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Unavailable { .. }), "{err}");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn keys_expire() {
+    let server = MockServer::start().await;
+    let kv = server.client();
+
+    kv.put_with_ttl("sessions", "abc", &1, Duration::from_secs(60))
+        .await
+        .unwrap();
+    let ttl = kv.ttl("sessions", "abc").await.unwrap().unwrap();
+    assert!(ttl <= Duration::from_secs(60) && ttl > Duration::from_secs(50));
+
+    kv.put("sessions", "def", &2).await.unwrap();
+    assert_eq!(kv.ttl("sessions", "def").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn sweeps_remove_expired_keys() {
+    let server = MockServer::start().await;
+    let sweeper = server.client().expiry_sweeper("sessions").page_size(2);
+    for key in ["a", "b", "c"] {
+        server.insert("sessions", key, json!({ "value": key, "expires_at": 1 }));
+    }
+    sweeper
+        .put_with_ttl("live", &"d", Duration::from_secs(60))
+        .await
+        .unwrap();
+    server.insert("sessions", "plain", json!({ "n": 1 }));
+    server.insert("sessions", "promo", json!({ "code": "x", "expires_at": 1 }));
+    assert_eq!(sweeper.get::<String>("a").await.unwrap(), None);
+
+    let report = sweeper.sweep().await.unwrap();
+    assert_eq!(report.succeeded, ["a", "b", "c"]);
+    assert!(report.is_success());
+    assert_eq!(server.keys("sessions"), ["live", "plain", "promo"]);
+    assert_eq!(
+        sweeper.get::<String>("live").await.unwrap().as_deref(),
+        Some("d")
+    );
+}
+
+#[tokio::test]
+async fn spawned_sweepers_run_until_dropped() {
+    let server = MockServer::start().await;
+    let expired = json!({ "value": 1, "expires_at": 1 });
+    server.insert("sessions", "old", expired.clone());
+
+    let handle = server
+        .client()
+        .expiry_sweeper("sessions")
+        .interval(Duration::from_millis(20))
+        .spawn();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !server.keys("sessions").is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("sweeper did not remove the expired key");
+
+    drop(handle);
+    server.insert("sessions", "older", expired);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(server.keys("sessions"), ["older"]);
+}
+
+#[tokio::test]
+async fn zero_sweep_intervals_are_clamped() {
+    let server = MockServer::start().await;
+    server.insert("sessions", "old", json!({ "value": 1, "expires_at": 1 }));
+
+    let handle = server
+        .client()
+        .expiry_sweeper("sessions")
+        .interval(Duration::ZERO)
+        .spawn();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !server.keys("sessions").is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("sweeper did not remove the expired key");
+    drop(handle);
+}
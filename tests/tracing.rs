@@ -0,0 +1,320 @@
+#![cfg(feature = "tracing")]
+
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use smolkv_client::testing::{Fault, MockServer};
+use smolkv_client::{Error, RetryPolicy, SmolKv, Transport, WaitOptions};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+#[derive(Debug, Default, Clone)]
+struct Captured {
+    name: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for Captured {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.fields
+            .insert(field.name().to_string(), format!("{value:?}"));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields
+            .insert(field.name().to_string(), value.to_string());
+    }
+}
+
+/// Keeps every span and event in memory.
+#[derive(Default, Clone)]
+struct Recorder {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<BTreeMap<u64, Captured>>>,
+    events: Arc<Mutex<Vec<Captured>>>,
+}
+
+impl Recorder {
+    fn spans(&self, name: &str) -> Vec<BTreeMap<String, String>> {
+        self.spans
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|span| span.name == name)
+            .map(|span| span.fields.clone())
+            .collect()
+    }
+
+    fn events(&self, message: &str) -> Vec<BTreeMap<String, String>> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.fields.get("message").map(String::as_str) == Some(message))
+            .map(|event| event.fields.clone())
+            .collect()
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut span = Captured {
+            name: attrs.metadata().name().to_string(),
+            ..Captured::default()
+        };
+        attrs.record(&mut span);
+        self.spans.lock().unwrap().insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, id: &Id, values: &Record<'_>) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+            values.record(span);
+        }
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut captured = Captured::default();
+        event.record(&mut captured);
+        self.events.lock().unwrap().push(captured);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+/// Answers every request with `{}` and keeps the `traceparent` headers it saw.
+#[derive(Default)]
+struct Headers {
+    seen: Mutex<Vec<String>>,
+}
+
+impl Transport for Headers {
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        if let Some(value) = request.headers().get("traceparent") {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(value.to_str().unwrap().to_string());
+        }
+        Box::pin(async { Ok(http::Response::new("{}").into()) })
+    }
+}
+
+#[tokio::test]
+async fn requests_are_traced() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let server = MockServer::start().await;
+    server.inject(Fault::status(503).times(1));
+    let kv = SmolKv::builder(server.url())
+        .retry_policy(RetryPolicy::new().base_delay(Duration::from_millis(5)))
+        .build()
+        .unwrap();
+    kv.put("users", "bob", &json!({ "age": 42 })).await.unwrap();
+
+    assert_eq!(recorder.spans("smolkv.put").len(), 1);
+    let requests = recorder.spans("smolkv.request");
+    let request = &requests[0];
+    assert_eq!(request["collection"], "users");
+    assert_eq!(request["key"], "bob");
+    assert_eq!(request["method"], "PUT");
+    assert_eq!(request["status"], "200");
+    assert_eq!(request["retries"], "1");
+    assert!(request.contains_key("latency_ms"));
+    assert!(request.contains_key("response_size"));
+    assert_eq!(recorder.events("retrying request").len(), 1);
+}
+
+#[tokio::test]
+async fn keys_can_be_redacted() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let server = MockServer::start().await;
+    let kv = SmolKv::builder(server.url())
+        .redact_keys(true)
+        .build()
+        .unwrap();
+    kv.put("users", "secret-key", &1).await.unwrap();
+
+    let key = &recorder.spans("smolkv.request")[0]["key"];
+    assert_ne!(key, "secret-key");
+    assert_eq!(key.len(), 8);
+}
+
+#[tokio::test]
+async fn traceparent_is_propagated() {
+    let transport = Arc::new(Headers::default());
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(transport.clone())
+        .build()
+        .unwrap();
+    kv.put("users", "bob", &1).await.unwrap();
+    kv.put("users", "bob", &2).await.unwrap();
+
+    let seen = transport.seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    let parts: Vec<&str> = seen[0].split('-').collect();
+    assert_eq!(parts[0], "00");
+    assert_eq!((parts[1].len(), parts[2].len()), (32, 16));
+    assert_ne!(seen[0], seen[1]);
+}
+
+#[tokio::test]
+async fn traces_continue_the_callers_context() {
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let transport = Arc::new(Headers::default());
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(transport.clone())
+        .trace_context(|| Some(format!("00-{TRACE_ID}-00f067aa0ba902b7-00")))
+        .build()
+        .unwrap();
+    kv.put("users", "bob", &1).await.unwrap();
+    kv.put("users", "bob", &2).await.unwrap();
+
+    let seen = transport.seen.lock().unwrap();
+    for traceparent in seen.iter() {
+        let parts: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(parts[1], TRACE_ID);
+        assert_ne!(parts[2], "00f067aa0ba902b7");
+        assert_eq!(parts[3], "00");
+    }
+    assert_ne!(seen[0], seen[1]);
+    let spans = recorder.spans("smolkv.request");
+    for (span, traceparent) in spans.iter().zip(seen.iter()) {
+        assert_eq!(span["trace_id"], TRACE_ID);
+        // Server spans hang off the recorded span, which hangs off the caller's.
+        assert_eq!(span["span_id"], traceparent.split('-').nth(2).unwrap());
+        assert_eq!(span["parent_span_id"], "00f067aa0ba902b7");
+    }
+}
+
+/// Fails the first two attempts with 503, keeping the `traceparent` of each.
+#[derive(Default)]
+struct Flaky {
+    seen: Mutex<Vec<String>>,
+}
+
+impl Transport for Flaky {
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        let mut seen = self.seen.lock().unwrap();
+        seen.push(
+            request.headers()["traceparent"]
+                .to_str()
+                .unwrap()
+                .to_string(),
+        );
+        let status = if seen.len() <= 2 { 503 } else { 200 };
+        let resp = http::Response::builder().status(status).body("{}").unwrap();
+        Box::pin(async { Ok(resp.into()) })
+    }
+}
+
+#[tokio::test]
+async fn retries_share_the_request_span_id() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let transport = Arc::new(Flaky::default());
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(transport.clone())
+        .retry_policy(RetryPolicy::new().base_delay(Duration::from_millis(5)))
+        .build()
+        .unwrap();
+    kv.put("users", "bob", &1).await.unwrap();
+
+    let seen = transport.seen.lock().unwrap();
+    assert_eq!(seen.len(), 3);
+    assert!(seen.iter().all(|traceparent| *traceparent == seen[0]));
+    let span = &recorder.spans("smolkv.request")[0];
+    assert_eq!(span["span_id"], seen[0].split('-').nth(2).unwrap());
+    assert!(!span.contains_key("parent_span_id"));
+}
+
+#[tokio::test]
+async fn invalid_trace_contexts_start_a_new_trace() {
+    let transport = Arc::new(Headers::default());
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(transport.clone())
+        .trace_context(|| Some("00-00000000000000000000000000000000-00f067aa0ba902b7-01".into()))
+        .build()
+        .unwrap();
+    kv.put("users", "bob", &1).await.unwrap();
+
+    let seen = transport.seen.lock().unwrap();
+    let parts: Vec<&str> = seen[0].split('-').collect();
+    assert_eq!(parts[1].len(), 32);
+    assert_ne!(parts[1], "00000000000000000000000000000000");
+}
+
+#[tokio::test]
+async fn job_and_subscription_events() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let server = MockServer::start().await;
+    let kv = server.client();
+    server.insert("users", "bob", json!(1));
+
+    let id = kv.start_backup("users").await.unwrap().id.unwrap();
+    kv.wait_for_backup("users", &id, WaitOptions::default())
+        .await
+        .unwrap();
+    let changes = recorder.events("job state changed");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["state"], "Completed");
+
+    server.inject(Fault::drop_stream(1).times(1));
+    let mut events = kv.subscribe_events("users");
+    use futures_util::StreamExt;
+    events.next().await.unwrap().unwrap();
+    server.insert("users", "alice", json!(2));
+    events.next().await.unwrap().unwrap();
+    assert_eq!(recorder.events("reconnecting subscription").len(), 1);
+}
+
+#[tokio::test]
+async fn failed_sweeps_are_logged() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let server = MockServer::start().await;
+    server.inject(Fault::status(500));
+    let _handle = SmolKv::builder(server.url())
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap()
+        .expiry_sweeper("sessions")
+        .interval(Duration::from_millis(20))
+        .spawn();
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while recorder.events("expiry sweep failed").is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("failed sweep was not logged");
+    assert_eq!(
+        recorder.events("expiry sweep failed")[0]["collection"],
+        "sessions"
+    );
+}
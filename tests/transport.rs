@@ -0,0 +1,140 @@
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use smolkv_client::testing::{Fault, MockServer};
+use smolkv_client::{
+    ConcurrencyLimitLayer, Error, RateLimitLayer, SharedTransport, SmolKv, TimeoutLayer, Transport,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Answers every request with the same JSON body, remembering what was asked.
+#[derive(Default)]
+struct Stub {
+    seen: Mutex<Vec<String>>,
+}
+
+impl Transport for Stub {
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        self.seen
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", request.method(), request.url().path()));
+        Box::pin(async {
+            let resp = http::Response::builder()
+                .status(200)
+                .body(json!({ "age": 42 }).to_string())
+                .unwrap();
+            Ok(resp.into())
+        })
+    }
+}
+
+/// Counts requests passing through it.
+struct Counter {
+    inner: SharedTransport,
+    count: Arc<AtomicUsize>,
+}
+
+impl Transport for Counter {
+    fn send(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        self.inner.send(request)
+    }
+}
+
+#[tokio::test]
+async fn custom_transports_replace_http() {
+    let stub = Arc::new(Stub::default());
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(stub.clone())
+        .build()
+        .unwrap();
+
+    let value: Value = kv.get("users", "bob").await.unwrap();
+    assert_eq!(value, json!({ "age": 42 }));
+    assert_eq!(*stub.seen.lock().unwrap(), ["GET /api/users/bob"]);
+}
+
+#[tokio::test]
+async fn layers_see_every_attempt() {
+    let server = MockServer::start().await;
+    server.inject(Fault::status(503).times(2));
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let counter = count.clone();
+    let kv = SmolKv::builder(server.url())
+        .layer(move |inner| {
+            Arc::new(Counter {
+                inner,
+                count: counter.clone(),
+            }) as SharedTransport
+        })
+        .layer(ConcurrencyLimitLayer::new(2))
+        .retry_policy(
+            smolkv_client::RetryPolicy::new()
+                .base_delay(Duration::from_millis(5))
+                .jitter(false),
+        )
+        .build()
+        .unwrap();
+
+    kv.put("users", "bob", &1).await.unwrap();
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn timeout_layer_reports_the_request() {
+    let server = MockServer::start().await;
+    server.inject(Fault::latency(Duration::from_millis(500)));
+    let kv = SmolKv::builder(server.url())
+        .layer(TimeoutLayer::new(Duration::from_millis(50)))
+        .retry_policy(smolkv_client::RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let err = kv.get::<Value>("users", "bob").await.unwrap_err();
+    let Error::Timeout(context) = err else {
+        panic!("expected a timeout, got {err}");
+    };
+    assert_eq!(context.key.as_deref(), Some("bob"));
+}
+
+/// Never answers.
+struct Hang;
+
+impl Transport for Hang {
+    fn send(&self, _: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response, Error>> {
+        Box::pin(futures_util::future::pending())
+    }
+}
+
+#[tokio::test]
+async fn client_timeouts_cover_custom_transports() {
+    let kv = SmolKv::builder("http://smolkv.invalid")
+        .transport(Hang)
+        .timeout(Duration::from_millis(50))
+        .retry_policy(smolkv_client::RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    let err = kv.get::<Value>("users", "bob").await.unwrap_err();
+    assert!(matches!(err, Error::Timeout(_)), "{err}");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn rate_limit_spaces_requests() {
+    let server = MockServer::start().await;
+    let kv = SmolKv::builder(server.url())
+        .layer(RateLimitLayer::new(10, Duration::from_secs(1)))
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    for i in 0..4 {
+        kv.put("users", &format!("user{i}"), &i).await.unwrap();
+    }
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}